@@ -1,154 +1,227 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion, BenchmarkId};
-use hft_orderbook::{OrderBook, Order, Side};
+use criterion::measurement::WallTime;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkGroup, BenchmarkId, Criterion};
+use hft_orderbook::{AvlIndex, BTreeIndex, InstrumentConfig, LadderIndex, OrderBook, Order, PriceLevelIndex, Side};
 use rand::{Rng, SeedableRng};
 use rand::rngs::StdRng;
 
+/// Empty book using price-level index `I`
+fn new_book<I: PriceLevelIndex>(order_capacity: usize, limit_capacity: usize) -> OrderBook<I> {
+    OrderBook::with_backend_and_capacity(InstrumentConfig::default(), order_capacity, limit_capacity)
+}
+
+/// Run a generic benchmark body once per price-level index backend
+macro_rules! for_each_backend {
+    ($bench:ident($($arg:expr),*)) => {
+        $bench::<AvlIndex>($($arg,)* "avl");
+        $bench::<BTreeIndex>($($arg,)* "btree");
+        $bench::<LadderIndex>($($arg,)* "ladder");
+    };
+}
+
+fn add_orders<I: PriceLevelIndex>(group: &mut BenchmarkGroup<WallTime>, size: usize, backend: &str) {
+    group.bench_with_input(BenchmarkId::new(format!("sequential/{}", backend), size), &size, |b, &size| {
+        b.iter(|| {
+            let mut book = new_book::<I>(size, size / 10);
+            book.set_time(1000);
+
+            for i in 0..size {
+                let order = Order::new(
+                    i as u64,
+                    if i % 2 == 0 { Side::Buy } else { Side::Sell },
+                    100,
+                    5000 + (i % 100) as i64,
+                    1000 + i as u64,
+                    1,
+                );
+                book.add_order(order).unwrap();
+            }
+            black_box(book);
+        });
+    });
+
+    group.bench_with_input(BenchmarkId::new(format!("random/{}", backend), size), &size, |b, &size| {
+        b.iter(|| {
+            let mut book = new_book::<I>(size, size / 10);
+            let mut rng = StdRng::seed_from_u64(42);
+            book.set_time(1000);
+
+            for i in 0..size {
+                let order = Order::new(
+                    i as u64,
+                    if rng.gen_bool(0.5) { Side::Buy } else { Side::Sell },
+                    rng.gen_range(1..1000),
+                    rng.gen_range(4900..5100),
+                    1000 + i as u64,
+                    1,
+                );
+                book.add_order(order).unwrap();
+            }
+            black_box(book);
+        });
+    });
+}
+
 fn benchmark_add_orders(c: &mut Criterion) {
     let mut group = c.benchmark_group("add_orders");
-    
-    for size in [100, 1000, 10000].iter() {
-        group.bench_with_input(BenchmarkId::new("sequential", size), size, |b, &size| {
-            b.iter(|| {
-                let mut book = OrderBook::with_capacity(size, size / 10);
+
+    for size in [100, 1000, 10000] {
+        for_each_backend!(add_orders(&mut group, size));
+    }
+    group.finish();
+}
+
+fn cancel_orders<I: PriceLevelIndex>(group: &mut BenchmarkGroup<WallTime>, size: usize, backend: &str) {
+    group.bench_with_input(BenchmarkId::new(format!("cancel/{}", backend), size), &size, |b, &size| {
+        b.iter_batched(
+            || {
+                let mut book = new_book::<I>(size, size / 10);
+                let mut rng = StdRng::seed_from_u64(42);
                 book.set_time(1000);
-                
+
+                let mut order_ids = Vec::new();
                 for i in 0..size {
+                    let order_id = i as u64;
                     let order = Order::new(
-                        i as u64,
-                        if i % 2 == 0 { Side::Buy } else { Side::Sell },
-                        100,
-                        5000 + (i % 100) as u64,
+                        order_id,
+                        if rng.gen_bool(0.5) { Side::Buy } else { Side::Sell },
+                        rng.gen_range(1..1000),
+                        rng.gen_range(4900..5100),
                         1000 + i as u64,
                         1,
                     );
-                    black_box(book.add_order(order).unwrap());
+                    book.add_order(order).unwrap();
+                    order_ids.push(order_id);
+                }
+                (book, order_ids)
+            },
+            |(mut book, order_ids)| {
+                for &order_id in &order_ids {
+                    if book.contains_order(order_id) {
+                        black_box(book.remove_order(order_id).unwrap());
+                    }
                 }
                 black_box(book);
-            });
-        });
-        
-        group.bench_with_input(BenchmarkId::new("random", size), size, |b, &size| {
-            b.iter(|| {
-                let mut book = OrderBook::with_capacity(size, size / 10);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+}
+
+fn benchmark_cancel_orders(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cancel_orders");
+
+    for size in [100, 1000, 10000] {
+        for_each_backend!(cancel_orders(&mut group, size));
+    }
+    group.finish();
+}
+
+fn cross_orders<I: PriceLevelIndex>(group: &mut BenchmarkGroup<WallTime>, size: usize, backend: &str) {
+    group.bench_with_input(BenchmarkId::new(format!("cross_orders/{}", backend), size), &size, |b, &size| {
+        b.iter_batched(
+            || {
+                let mut book = new_book::<I>(size * 2, size / 5);
                 let mut rng = StdRng::seed_from_u64(42);
                 book.set_time(1000);
-                
+
+                // Add resting orders
                 for i in 0..size {
                     let order = Order::new(
                         i as u64,
-                        if rng.gen_bool(0.5) { Side::Buy } else { Side::Sell },
+                        Side::Sell,
                         rng.gen_range(1..1000),
-                        rng.gen_range(4900..5100),
+                        5000 + (i % 100) as i64,
                         1000 + i as u64,
                         1,
                     );
-                    black_box(book.add_order(order).unwrap());
+                    book.add_order(order).unwrap();
+                }
+                book
+            },
+            |mut book| {
+                // Add crossing orders
+                let mut rng = StdRng::seed_from_u64(43);
+                for i in 0..size {
+                    let order = Order::new(
+                        (size + i) as u64,
+                        Side::Buy,
+                        rng.gen_range(1..1000),
+                        5050, // Cross the spread
+                        2000 + i as u64,
+                        1,
+                    );
+                    black_box(book.submit(order).unwrap());
                 }
                 black_box(book);
-            });
-        });
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+}
+
+fn benchmark_matching(c: &mut Criterion) {
+    let mut group = c.benchmark_group("matching");
+
+    for size in [100, 1000, 10000] {
+        for_each_backend!(cross_orders(&mut group, size));
     }
     group.finish();
 }
 
-fn benchmark_cancel_orders(c: &mut Criterion) {
-    let mut group = c.benchmark_group("cancel_orders");
-    
-    for size in [100, 1000, 10000].iter() {
-        group.bench_with_input(BenchmarkId::new("cancel", size), size, |b, &size| {
-            b.iter_batched(
-                || {
-                    let mut book = OrderBook::with_capacity(size, size / 10);
-                    let mut rng = StdRng::seed_from_u64(42);
-                    book.set_time(1000);
-                    
-                    let mut order_ids = Vec::new();
-                    for i in 0..size {
-                        let order_id = i as u64;
-                        let order = Order::new(
-                            order_id,
-                            if rng.gen_bool(0.5) { Side::Buy } else { Side::Sell },
-                            rng.gen_range(1..1000),
-                            rng.gen_range(4900..5100),
-                            1000 + i as u64,
-                            1,
-                        );
-                        book.add_order(order).unwrap();
-                        order_ids.push(order_id);
-                    }
-                    (book, order_ids)
-                },
-                |(mut book, order_ids)| {
-                    for &order_id in &order_ids {
-                        if book.contains_order(order_id) {
-                            black_box(book.cancel_order(order_id).unwrap());
-                        }
-                    }
-                    black_box(book);
-                },
-                criterion::BatchSize::SmallInput,
-            );
-        });
+/// Book with one resting sell order per level, at consecutive prices from 5000
+fn ask_ladder<I: PriceLevelIndex>(levels: usize) -> OrderBook<I> {
+    let mut book = new_book::<I>(levels, levels);
+    for i in 0..levels {
+        book.add_order(Order::new(i as u64, Side::Sell, 10, 5000 + i as i64, i as u64, 1)).unwrap();
     }
-    group.finish();
+    book
 }
 
-fn benchmark_matching(c: &mut Criterion) {
-    let mut group = c.benchmark_group("matching");
-    
-    for size in [100, 1000, 10000].iter() {
-        group.bench_with_input(BenchmarkId::new("cross_orders", size), size, |b, &size| {
-            b.iter_batched(
-                || {
-                    let mut book = OrderBook::with_capacity(size * 2, size / 5);
-                    let mut rng = StdRng::seed_from_u64(42);
-                    book.set_time(1000);
-                    
-                    // Add resting orders
-                    for i in 0..size {
-                        let order = Order::new(
-                            i as u64,
-                            Side::Sell,
-                            rng.gen_range(1..1000),
-                            5000 + (i % 100) as u64,
-                            1000 + i as u64,
-                            1,
-                        );
-                        book.add_order(order).unwrap();
-                    }
-                    book
-                },
-                |mut book| {
-                    // Add crossing orders
-                    let mut rng = StdRng::seed_from_u64(43);
-                    for i in 0..size {
-                        let order = Order::new(
-                            (size + i) as u64,
-                            Side::Buy,
-                            rng.gen_range(1..1000),
-                            5050, // Cross the spread
-                            2000 + i as u64,
-                            1,
-                        );
-                        black_box(book.add_order(order).unwrap());
-                    }
-                    black_box(book);
-                },
-                criterion::BatchSize::SmallInput,
-            );
-        });
+fn sweeps<I: PriceLevelIndex>(group: &mut BenchmarkGroup<WallTime>, levels: usize, backend: &str) {
+    // One aggressive order consuming every level, best first
+    group.bench_with_input(BenchmarkId::new(format!("sweep_all_levels/{}", backend), levels), &levels, |b, &levels| {
+        b.iter_batched(
+            || ask_ladder::<I>(levels),
+            |mut book| {
+                let order = Order::new(levels as u64, Side::Buy, 10 * levels as u64, 5000 + levels as i64, 0, 1);
+                black_box(book.submit(order).unwrap());
+                black_box(book);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+
+    // Cancelling the top of book over and over, so the best level keeps emptying
+    group.bench_with_input(BenchmarkId::new(format!("cancel_best/{}", backend), levels), &levels, |b, &levels| {
+        b.iter_batched(
+            || ask_ladder::<I>(levels),
+            |mut book| {
+                for order_id in 0..levels as u64 {
+                    black_box(book.remove_order(order_id).unwrap());
+                    black_box(book.best_ask());
+                }
+                black_box(book);
+            },
+            criterion::BatchSize::SmallInput,
+        );
+    });
+}
+
+fn benchmark_sweeps(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sweeps");
+
+    for levels in [100, 1000, 10000] {
+        for_each_backend!(sweeps(&mut group, levels));
     }
     group.finish();
 }
 
-fn benchmark_queries(c: &mut Criterion) {
-    let mut group = c.benchmark_group("queries");
-    
+fn queries<I: PriceLevelIndex>(group: &mut BenchmarkGroup<WallTime>, backend: &str) {
     // Setup a book with many orders
-    let mut book = OrderBook::with_capacity(10000, 1000);
+    let mut book = new_book::<I>(10000, 1000);
     let mut rng = StdRng::seed_from_u64(42);
     book.set_time(1000);
-    
+
     for i in 0..10000 {
         let order = Order::new(
             i as u64,
@@ -160,27 +233,35 @@ fn benchmark_queries(c: &mut Criterion) {
         );
         book.add_order(order).unwrap();
     }
-    
-    group.bench_function("best_bid", |b| {
+
+    group.bench_function(format!("best_bid/{}", backend), |b| {
         b.iter(|| black_box(book.best_bid()));
     });
-    
-    group.bench_function("best_ask", |b| {
+
+    group.bench_function(format!("best_ask/{}", backend), |b| {
         b.iter(|| black_box(book.best_ask()));
     });
-    
-    group.bench_function("spread", |b| {
+
+    group.bench_function(format!("spread/{}", backend), |b| {
         b.iter(|| black_box(book.spread()));
     });
-    
-    group.bench_function("mid_price", |b| {
+
+    group.bench_function(format!("mid_price/{}", backend), |b| {
         b.iter(|| black_box(book.mid_price()));
     });
-    
-    group.bench_function("volume_at_price", |b| {
+
+    group.bench_function(format!("volume_at_price/{}", backend), |b| {
         b.iter(|| black_box(book.volume_at_price(5000)));
     });
-    
+
+    group.bench_function(format!("top_10_levels/{}", backend), |b| {
+        b.iter(|| black_box(book.get_levels(Some(10))));
+    });
+}
+
+fn benchmark_queries(c: &mut Criterion) {
+    let mut group = c.benchmark_group("queries");
+    for_each_backend!(queries(&mut group));
     group.finish();
 }
 
@@ -189,6 +270,7 @@ criterion_group!(
     benchmark_add_orders,
     benchmark_cancel_orders,
     benchmark_matching,
+    benchmark_sweeps,
     benchmark_queries
 );
 criterion_main!(benches);
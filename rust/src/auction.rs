@@ -0,0 +1,279 @@
+//! Call auctions for opening and closing the book
+//!
+//! While the book is in the `AuctionCall` phase, `OrderBook::submit` rests
+//! limit orders without matching them, so the book may become crossed.
+//! `OrderBook::uncross` then executes everything that can trade at a single
+//! equilibrium price and moves the book to continuous trading.
+//!
+//! The equilibrium price is chosen among the prices of the crossing levels:
+//!
+//! 1. the price executing the most volume,
+//! 2. then the one leaving the smallest imbalance between buy and sell
+//!    interest at that price,
+//! 3. then the one closest to the auction's reference price, if any,
+//! 4. then the lowest of the remaining prices.
+//!
+//! The same calculation backs the indicative price and volume published while
+//! the auction is still collecting orders.
+//!
+//! Self-trade prevention does not apply to the uncross. An auction trade has
+//! no incoming order whose mode could decide, and cancelling orders mid-uncross
+//! would leave the book short of the volume the price was chosen for, so
+//! orders of the same owner can trade with each other here.
+
+use crate::level_index::PriceLevelIndex;
+use crate::matching::ExecutionReport;
+use crate::orderbook::OrderBook;
+use crate::types::{Price, Quantity, Side, Trade, TradingPhase};
+use crate::Result;
+use std::cmp::Reverse;
+
+#[cfg(feature = "serde_support")]
+use serde::{Deserialize, Serialize};
+
+/// Price at which a crossed book would uncross, and how much would trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct IndicativeUncross {
+    /// Equilibrium price
+    pub price: Price,
+    /// Quantity executed at `price`
+    pub volume: Quantity,
+    /// Buy quantity willing to trade at `price` that would be left over
+    pub buy_surplus: Quantity,
+    /// Sell quantity willing to trade at `price` that would be left over
+    pub sell_surplus: Quantity,
+}
+
+impl IndicativeUncross {
+    /// Unmatched quantity at the equilibrium price, on whichever side has more
+    pub fn imbalance(&self) -> Quantity {
+        self.buy_surplus.max(self.sell_surplus)
+    }
+
+    /// Side with unmatched interest at the equilibrium price, if any
+    pub fn imbalance_side(&self) -> Option<Side> {
+        if self.buy_surplus > 0 {
+            Some(Side::Buy)
+        } else if self.sell_surplus > 0 {
+            Some(Side::Sell)
+        } else {
+            None
+        }
+    }
+}
+
+/// Outcome of uncrossing a call auction
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct AuctionResult {
+    /// Equilibrium price, or `None` if the book was not crossed
+    pub price: Option<Price>,
+    /// Total quantity executed
+    pub volume: Quantity,
+    /// Trades at `price` in price-time priority
+    ///
+    /// Auction trades have no aggressor: each lists the buy order as
+    /// `aggressor_order_id` and the sell order as `passive_order_id`.
+    pub trades: Vec<Trade>,
+    /// Stop orders triggered by the auction price once trading resumed, in
+    /// firing order
+    pub triggered: Vec<ExecutionReport>,
+}
+
+impl<I: PriceLevelIndex> OrderBook<I> {
+    /// Move the book to the `AuctionCall` phase
+    ///
+    /// Until `uncross` is called, `submit` rests limit orders without matching
+    /// them and rejects order types that cannot rest. `reference_price`, usually
+    /// the previous close or last trade, breaks ties between equilibrium prices.
+    /// Calling it again during the auction only replaces the reference price.
+    pub fn start_call_auction(&mut self, reference_price: Option<Price>) -> Result<()> {
+        self.set_phase(TradingPhase::AuctionCall)?;
+        self.auction_reference_price = reference_price;
+        Ok(())
+    }
+
+    /// Price and volume the book would uncross at right now
+    ///
+    /// Returns `None` if the book is not crossed. Uses the running auction's
+    /// reference price for tie breaks, and none outside an auction.
+    pub fn indicative_uncross(&self) -> Option<IndicativeUncross> {
+        self.equilibrium(self.auction_reference_price)
+    }
+
+    /// Indicative equilibrium price of the book
+    pub fn indicative_price(&self) -> Option<Price> {
+        self.indicative_uncross().map(|uncross| uncross.price)
+    }
+
+    /// Quantity that would execute at the indicative price
+    pub fn indicative_volume(&self) -> Quantity {
+        self.indicative_uncross().map_or(0, |uncross| uncross.volume)
+    }
+
+    /// End the call auction, executing all crossing interest at one price
+    ///
+    /// Bids are consumed from the highest price and asks from the lowest, FIFO
+    /// within each level, so both sides trade in price-time priority, without
+    /// self-trade prevention (see the module docs). Whatever is left rests and
+    /// the book moves to the `Continuous` phase, where stops triggered by the
+    /// auction price are then submitted.
+    pub fn uncross(&mut self) -> Result<AuctionResult> {
+        self.check_phase(self.phase == TradingPhase::AuctionCall)?;
+
+        let mut result = AuctionResult { price: None, volume: 0, trades: Vec::new(), triggered: Vec::new() };
+        let Some(uncross) = self.equilibrium(self.auction_reference_price) else {
+            self.enter_phase(TradingPhase::Continuous);
+            result.triggered = self.fire_stops();
+            return Ok(result);
+        };
+        result.price = Some(uncross.price);
+        result.volume = uncross.volume;
+
+        let now = self.current_time;
+        let bbo_before = self.bbo_before();
+        let mut remaining = uncross.volume;
+        while remaining > 0 {
+            let bid_limit = self.best_bid_index.expect("bids cover the auction volume");
+            let ask_limit = self.best_ask_index.expect("asks cover the auction volume");
+            let bid_idx = self.limits[bid_limit].as_ref().unwrap().head_order_index.unwrap();
+            let ask_idx = self.limits[ask_limit].as_ref().unwrap().head_order_index.unwrap();
+
+            let bid = self.orders[bid_idx].as_ref().unwrap();
+            let ask = self.orders[ask_idx].as_ref().unwrap();
+            let (bid_id, ask_id) = (bid.id, ask.id);
+            let quantity = remaining.min(bid.quantity).min(ask.quantity);
+
+            result.trades.push(Trade::new(bid_id, ask_id, uncross.price, quantity, now, Side::Buy));
+            self.fill_resting_order(bid_idx, bid_limit, quantity, ask_id);
+            self.fill_resting_order(ask_idx, ask_limit, quantity, bid_id);
+            remaining -= quantity;
+        }
+        self.emit_bbo_change(bbo_before);
+        self.last_trade_price = Some(uncross.price);
+        self.enter_phase(TradingPhase::Continuous);
+        result.triggered = self.fire_stops();
+
+        Ok(result)
+    }
+
+    /// Best uncrossing price among the crossing levels
+    fn equilibrium(&self, reference_price: Option<Price>) -> Option<IndicativeUncross> {
+        let best_bid = self.bids_with_hidden().next()?.price;
+        let best_ask = self.asks_with_hidden().next()?.price;
+        if best_bid < best_ask {
+            return None;
+        }
+
+        // Only levels on the other side of the opposite best can trade; hidden
+        // orders and iceberg reserves count in full since they all execute
+        let mut bids: Vec<(Price, Quantity)> = self.bids_with_hidden()
+            .take_while(|limit| limit.price >= best_ask)
+            .map(|limit| (limit.price, limit.total_size()))
+            .collect();
+        let asks: Vec<(Price, Quantity)> = self.asks_with_hidden()
+            .take_while(|limit| limit.price <= best_bid)
+            .map(|limit| (limit.price, limit.total_size()))
+            .collect();
+        // Both sides are walked from the lowest price
+        bids.reverse();
+
+        let mut prices: Vec<Price> = bids.iter().chain(&asks).map(|&(price, _)| price).collect();
+        prices.sort_unstable();
+        prices.dedup();
+
+        // Walk candidate prices upwards: buy interest at or above the price
+        // shrinks while sell interest at or below it grows
+        let mut demand: Quantity = bids.iter().map(|&(_, size)| size).sum();
+        let mut supply: Quantity = 0;
+        let (mut next_bid, mut next_ask) = (0, 0);
+        prices
+            .into_iter()
+            .map(|price| {
+                while next_bid < bids.len() && bids[next_bid].0 < price {
+                    demand -= bids[next_bid].1;
+                    next_bid += 1;
+                }
+                while next_ask < asks.len() && asks[next_ask].0 <= price {
+                    supply += asks[next_ask].1;
+                    next_ask += 1;
+                }
+                let volume = demand.min(supply);
+                IndicativeUncross {
+                    price,
+                    volume,
+                    buy_surplus: demand - volume,
+                    sell_surplus: supply - volume,
+                }
+            })
+            .min_by_key(|uncross| {
+                let distance = reference_price.map_or(0, |reference| uncross.price.abs_diff(reference));
+                (Reverse(uncross.volume), uncross.imbalance(), distance)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SelfTradePrevention;
+    use crate::Order;
+
+    fn order(id: u64, side: Side, quantity: Quantity, price: Price) -> Order {
+        Order::new(id, side, quantity, price, id, 1)
+    }
+
+    #[test]
+    fn test_maximum_volume_wins() {
+        let mut book = OrderBook::new();
+        book.start_call_auction(None).unwrap();
+        book.submit(order(1, Side::Buy, 10, 103)).unwrap();
+        book.submit(order(2, Side::Buy, 20, 101)).unwrap();
+        book.submit(order(3, Side::Sell, 15, 100)).unwrap();
+        book.submit(order(4, Side::Sell, 15, 102)).unwrap();
+
+        // 100: 30 vs 15, 101: 30 vs 15, 102: 10 vs 30, 103: 10 vs 30
+        let uncross = book.indicative_uncross().unwrap();
+        assert_eq!(uncross.volume, 15);
+        assert_eq!(uncross.price, 100);
+        assert_eq!(uncross.imbalance_side(), Some(Side::Buy));
+        assert_eq!(uncross.imbalance(), 15);
+    }
+
+    #[test]
+    fn test_imbalance_then_reference_break_ties() {
+        let mut book = OrderBook::new();
+        book.start_call_auction(Some(104)).unwrap();
+        book.submit(order(1, Side::Buy, 10, 105)).unwrap();
+        book.submit(order(2, Side::Sell, 10, 100)).unwrap();
+
+        // Every candidate executes 10 with no surplus; the reference decides
+        assert_eq!(book.indicative_price(), Some(105));
+        book.start_call_auction(Some(101)).unwrap();
+        assert_eq!(book.indicative_price(), Some(100));
+        book.start_call_auction(None).unwrap();
+        assert_eq!(book.indicative_price(), Some(100));
+
+        // A surplus at one candidate moves the price away from it
+        book.submit(order(3, Side::Buy, 5, 100)).unwrap();
+        book.start_call_auction(Some(100)).unwrap();
+        let uncross = book.indicative_uncross().unwrap();
+        assert_eq!((uncross.price, uncross.volume, uncross.imbalance()), (105, 10, 0));
+    }
+
+    #[test]
+    fn test_auction_ignores_self_trade_prevention() {
+        let mut book = OrderBook::new();
+        book.start_call_auction(None).unwrap();
+        book.submit(order(1, Side::Sell, 10, 100).with_owner(7)).unwrap();
+        let buy = order(2, Side::Buy, 10, 100)
+            .with_owner(7)
+            .with_self_trade_prevention(SelfTradePrevention::CancelBoth);
+        book.submit(buy).unwrap();
+
+        let result = book.uncross().unwrap();
+        assert_eq!(result.volume, 10);
+        assert_eq!((result.trades[0].aggressor_order_id, result.trades[0].passive_order_id), (2, 1));
+    }
+}
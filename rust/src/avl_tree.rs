@@ -1,9 +1,16 @@
 //! AVL Tree implementation for maintaining price levels in sorted order
 
+use crate::instrument::InstrumentConfig;
+use crate::level_index::PriceLevelIndex;
 use crate::types::Price;
+use std::collections::HashMap;
+
+#[cfg(feature = "serde_support")]
+use serde::{Deserialize, Serialize};
 
 /// AVL Tree node indices and operations
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
 pub struct AvlNode {
     /// Index of parent node (None for root)
     pub parent: Option<usize>,
@@ -202,3 +209,303 @@ pub trait AvlTree<T> {
         }
     }
 }
+
+/// A tree node together with the price it is keyed on
+#[derive(Debug, Clone, Copy)]
+struct AvlEntry {
+    price: Price,
+    node: AvlNode,
+}
+
+/// Price-level index backed by an AVL tree
+///
+/// Nodes are stored by limit index, so the tree links are the limit indices
+/// themselves. A price hash gives O(1) lookup of existing levels; the tree
+/// keeps them ordered for best-price and next-level queries.
+#[derive(Debug, Clone, Default)]
+pub struct AvlIndex {
+    /// Tree nodes indexed by limit index
+    nodes: Vec<Option<AvlEntry>>,
+    /// Map from price to limit index
+    by_price: HashMap<Price, usize>,
+    /// Root of the tree
+    root: Option<usize>,
+}
+
+impl AvlIndex {
+    fn entry(&self, index: usize) -> &AvlEntry {
+        self.nodes[index]
+            .as_ref()
+            .unwrap_or_else(|| panic!("Tree node at index {} should exist", index))
+    }
+
+    /// Insert a node into the subtree at `root`, rebalancing on the way back up
+    fn insert_into_tree(&mut self, root: Option<usize>, index: usize) -> usize {
+        match root {
+            None => index,
+            Some(root_idx) => {
+                let price = self.get_price(index);
+                let root_price = self.get_price(root_idx);
+
+                if price < root_price {
+                    let new_left = self.insert_into_tree(self.get_node(root_idx).left_child, index);
+                    self.get_node_mut(root_idx).left_child = Some(new_left);
+                    self.get_node_mut(new_left).parent = Some(root_idx);
+                } else if price > root_price {
+                    let new_right = self.insert_into_tree(self.get_node(root_idx).right_child, index);
+                    self.get_node_mut(root_idx).right_child = Some(new_right);
+                    self.get_node_mut(new_right).parent = Some(root_idx);
+                }
+
+                self.balance(root_idx)
+            }
+        }
+    }
+
+    /// Remove a node from the subtree at `root`, rebalancing on the way back up
+    fn remove_from_tree(&mut self, root: Option<usize>, index: usize) -> Option<usize> {
+        let root_idx = root?;
+        if root_idx != index {
+            if self.get_price(index) < self.get_price(root_idx) {
+                let new_left = self.remove_from_tree(self.get_node(root_idx).left_child, index);
+                self.get_node_mut(root_idx).left_child = new_left;
+            } else {
+                let new_right = self.remove_from_tree(self.get_node(root_idx).right_child, index);
+                self.get_node_mut(root_idx).right_child = new_right;
+            }
+            return Some(self.balance(root_idx));
+        }
+
+        let node = *self.get_node(root_idx);
+        match (node.left_child, node.right_child) {
+            (None, None) => None,
+            (Some(child), None) | (None, Some(child)) => {
+                self.get_node_mut(child).parent = node.parent;
+                Some(child)
+            }
+            (Some(left), Some(right)) => {
+                // Detach the successor (minimum of the right subtree) and splice
+                // it into this node's position. Node indices are limit indices,
+                // so the node itself must move rather than just its price.
+                let successor_idx = self.find_min(right);
+                let new_right = self.remove_from_tree(Some(right), successor_idx);
+
+                let successor = self.get_node_mut(successor_idx);
+                successor.parent = node.parent;
+                successor.left_child = Some(left);
+                successor.right_child = new_right;
+
+                self.get_node_mut(left).parent = Some(successor_idx);
+                if let Some(new_right) = new_right {
+                    self.get_node_mut(new_right).parent = Some(successor_idx);
+                }
+
+                // Rotations below consult the parent's child links, so
+                // point the parent at the successor before balancing
+                if let Some(parent_idx) = node.parent {
+                    let parent_node = self.get_node_mut(parent_idx);
+                    if parent_node.left_child == Some(root_idx) {
+                        parent_node.left_child = Some(successor_idx);
+                    } else {
+                        parent_node.right_child = Some(successor_idx);
+                    }
+                }
+
+                Some(self.balance(successor_idx))
+            }
+        }
+    }
+
+    /// In-order successor of a node, following parent links
+    fn successor(&self, index: usize) -> Option<usize> {
+        if let Some(right) = self.get_node(index).right_child {
+            return Some(self.find_min(right));
+        }
+
+        let mut child = index;
+        let mut parent = self.get_node(index).parent;
+        while let Some(parent_idx) = parent {
+            let parent_node = self.get_node(parent_idx);
+            if parent_node.left_child == Some(child) {
+                return Some(parent_idx);
+            }
+            child = parent_idx;
+            parent = parent_node.parent;
+        }
+        None
+    }
+
+    /// In-order predecessor of a node, following parent links
+    fn predecessor(&self, index: usize) -> Option<usize> {
+        if let Some(left) = self.get_node(index).left_child {
+            return Some(self.find_max(left));
+        }
+
+        let mut child = index;
+        let mut parent = self.get_node(index).parent;
+        while let Some(parent_idx) = parent {
+            let parent_node = self.get_node(parent_idx);
+            if parent_node.right_child == Some(child) {
+                return Some(parent_idx);
+            }
+            child = parent_idx;
+            parent = parent_node.parent;
+        }
+        None
+    }
+
+    /// Closest node strictly above (`above`) or below `price`, by descent from the root
+    fn search_beyond(&self, price: Price, above: bool) -> Option<usize> {
+        let mut best = None;
+        let mut current = self.root;
+        while let Some(idx) = current {
+            let node = self.get_node(idx);
+            let node_price = self.get_price(idx);
+            current = if above && node_price > price {
+                best = Some(idx);
+                node.left_child
+            } else if !above && node_price < price {
+                best = Some(idx);
+                node.right_child
+            } else if above {
+                node.right_child
+            } else {
+                node.left_child
+            };
+        }
+        best
+    }
+}
+
+impl AvlTree<Price> for AvlIndex {
+    fn get_price(&self, index: usize) -> Price {
+        self.entry(index).price
+    }
+
+    fn get_node(&self, index: usize) -> &AvlNode {
+        &self.entry(index).node
+    }
+
+    fn get_node_mut(&mut self, index: usize) -> &mut AvlNode {
+        &mut self.nodes[index]
+            .as_mut()
+            .unwrap_or_else(|| panic!("Tree node at index {} should exist", index))
+            .node
+    }
+}
+
+impl PriceLevelIndex for AvlIndex {
+    fn for_config(_config: &InstrumentConfig) -> Self {
+        Self::default()
+    }
+
+    fn get(&self, price: Price) -> Option<usize> {
+        self.by_price.get(&price).copied()
+    }
+
+    fn insert(&mut self, price: Price, limit_idx: usize) {
+        let previous = self.by_price.insert(price, limit_idx);
+        debug_assert!(previous.is_none(), "level {} already indexed", price);
+
+        if self.nodes.len() <= limit_idx {
+            self.nodes.resize(limit_idx + 1, None);
+        }
+        self.nodes[limit_idx] = Some(AvlEntry { price, node: AvlNode::new() });
+        self.root = Some(self.insert_into_tree(self.root, limit_idx));
+    }
+
+    fn remove(&mut self, price: Price) -> Option<usize> {
+        let limit_idx = self.by_price.remove(&price)?;
+        self.root = self.remove_from_tree(self.root, limit_idx);
+        self.nodes[limit_idx] = None;
+        Some(limit_idx)
+    }
+
+    fn len(&self) -> usize {
+        self.by_price.len()
+    }
+
+    fn lowest(&self) -> Option<usize> {
+        self.root.map(|root| self.find_min(root))
+    }
+
+    fn highest(&self) -> Option<usize> {
+        self.root.map(|root| self.find_max(root))
+    }
+
+    fn next_higher(&self, price: Price) -> Option<usize> {
+        match self.by_price.get(&price) {
+            Some(&idx) => self.successor(idx),
+            None => self.search_beyond(price, true),
+        }
+    }
+
+    fn next_lower(&self, price: Price) -> Option<usize> {
+        match self.by_price.get(&price) {
+            Some(&idx) => self.predecessor(idx),
+            None => self.search_beyond(price, false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Check parent links, ordering and AVL balance; returns the subtree height
+    fn check_subtree(index: &AvlIndex, node_idx: Option<usize>, parent: Option<usize>) -> i32 {
+        let Some(idx) = node_idx else {
+            return 0;
+        };
+        let price = index.get_price(idx);
+        let node = *index.get_node(idx);
+        assert_eq!(node.parent, parent, "parent link of {}", price);
+
+        if let Some(left) = node.left_child {
+            assert!(index.get_price(left) < price);
+        }
+        if let Some(right) = node.right_child {
+            assert!(index.get_price(right) > price);
+        }
+
+        let left_height = check_subtree(index, node.left_child, Some(idx));
+        let right_height = check_subtree(index, node.right_child, Some(idx));
+        assert!((right_height - left_height).abs() <= 1, "unbalanced at {}", price);
+        assert_eq!(node.height, 1 + left_height.max(right_height));
+        node.height
+    }
+
+    #[test]
+    fn test_tree_stays_balanced() {
+        let mut index = AvlIndex::default();
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut free: Vec<usize> = (0..400).rev().collect();
+
+        for step in 0..4000 {
+            let price = rng.gen_range(1..400);
+            match index.get(price) {
+                Some(idx) => {
+                    assert_eq!(index.remove(price), Some(idx));
+                    free.push(idx);
+                }
+                None => index.insert(price, free.pop().unwrap()),
+            }
+
+            if step % 100 == 0 {
+                check_subtree(&index, index.root, None);
+            }
+        }
+
+        check_subtree(&index, index.root, None);
+        let mut prices = Vec::new();
+        let mut next = index.lowest();
+        while let Some(idx) = next {
+            prices.push(index.get_price(idx));
+            next = index.next_higher(index.get_price(idx));
+        }
+        assert!(prices.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(prices.len(), index.len());
+    }
+}
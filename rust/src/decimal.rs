@@ -0,0 +1,256 @@
+//! Exact conversion between decimal notation and integer book units
+//!
+//! The book stores prices and quantities as integer counts of the smallest unit.
+//! A `DecimalScale` fixes how many decimal places one unit represents (e.g. a
+//! price scale of 2 means one unit is 0.01), and converts between those units
+//! and decimal strings without going through floating point. Input with more
+//! precision than the scale allows is rejected rather than rounded.
+//!
+//! Quantities are unsigned and use `parse`/`decimal`; prices may be negative
+//! and use `parse_signed`/`signed_decimal`.
+
+use crate::{OrderBookError, Result};
+use std::fmt;
+
+#[cfg(feature = "serde_support")]
+use serde::{Deserialize, Serialize};
+
+/// Number of decimal places represented by one integer unit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct DecimalScale {
+    decimals: u32,
+}
+
+impl DecimalScale {
+    /// Largest supported number of decimal places
+    pub const MAX_DECIMALS: u32 = 18;
+
+    /// Create a scale with `decimals` decimal places
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decimals` exceeds `MAX_DECIMALS`.
+    pub const fn new(decimals: u32) -> Self {
+        assert!(decimals <= Self::MAX_DECIMALS, "decimal scale too large");
+        Self { decimals }
+    }
+
+    /// Number of decimal places
+    pub fn decimals(&self) -> u32 {
+        self.decimals
+    }
+
+    /// Number of units in one whole (10^decimals)
+    pub fn factor(&self) -> u64 {
+        10u64.pow(self.decimals)
+    }
+
+    /// Parse a decimal string such as "50.25" into units
+    ///
+    /// Trailing zeros beyond the scale are accepted; any other extra precision,
+    /// signs, exponents and whitespace are rejected.
+    pub fn parse(&self, s: &str) -> Result<u64> {
+        self.parse_magnitude(s, s)
+    }
+
+    /// Parse a decimal string with an optional leading `-`, such as "-1.25"
+    pub fn parse_signed(&self, s: &str) -> Result<i64> {
+        let invalid = || OrderBookError::InvalidDecimal(s.to_string());
+        match s.strip_prefix('-') {
+            Some(magnitude) => {
+                let units = self.parse_magnitude(magnitude, s)?;
+                0i64.checked_sub_unsigned(units).ok_or_else(invalid)
+            }
+            None => i64::try_from(self.parse_magnitude(s, s)?).map_err(|_| invalid()),
+        }
+    }
+
+    /// Parse an unsigned decimal, reporting errors against `original`
+    fn parse_magnitude(&self, s: &str, original: &str) -> Result<u64> {
+        let invalid = || OrderBookError::InvalidDecimal(original.to_string());
+
+        let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(invalid());
+        }
+        if !whole.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let significant = fraction.trim_end_matches('0');
+        if significant.len() > self.decimals as usize {
+            return Err(invalid());
+        }
+
+        let mut units: u64 = 0;
+        let digits = whole.bytes().chain(significant.bytes());
+        let padding = self.decimals as usize - significant.len();
+        for digit in digits.chain(std::iter::repeat_n(b'0', padding)) {
+            units = units
+                .checked_mul(10)
+                .and_then(|u| u.checked_add((digit - b'0') as u64))
+                .ok_or_else(invalid)?;
+        }
+        Ok(units)
+    }
+
+    /// Convert a float to units via its shortest decimal representation
+    ///
+    /// `50.25_f64` becomes exactly 5025 at scale 2; values that need more
+    /// decimal places than the scale provides are rejected, not rounded.
+    pub fn from_f64(&self, value: f64) -> Result<u64> {
+        if !value.is_finite() {
+            return Err(OrderBookError::InvalidDecimal(value.to_string()));
+        }
+        self.parse(&value.to_string())
+    }
+
+    /// Approximate value of `units` as a float
+    pub fn to_f64(&self, units: u64) -> f64 {
+        units as f64 / self.factor() as f64
+    }
+
+    /// Wrap unsigned `units` for decimal display
+    pub fn decimal(&self, units: u64) -> Decimal {
+        Decimal {
+            negative: false,
+            magnitude: units,
+            scale: *self,
+        }
+    }
+
+    /// Wrap signed `units` for decimal display
+    pub fn signed_decimal(&self, units: i64) -> Decimal {
+        Decimal {
+            negative: units < 0,
+            magnitude: units.unsigned_abs(),
+            scale: *self,
+        }
+    }
+}
+
+/// An integer unit count paired with its scale, displayed in decimal notation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct Decimal {
+    negative: bool,
+    magnitude: u64,
+    scale: DecimalScale,
+}
+
+impl Decimal {
+    /// Parse a possibly negative decimal string at the given scale
+    pub fn parse(s: &str, scale: DecimalScale) -> Result<Self> {
+        scale.parse_signed(s).map(|units| scale.signed_decimal(units))
+    }
+
+    /// Raw unit count
+    pub fn units(&self) -> i128 {
+        if self.negative {
+            -(self.magnitude as i128)
+        } else {
+            self.magnitude as i128
+        }
+    }
+
+    /// Scale of the value
+    pub fn scale(&self) -> DecimalScale {
+        self.scale
+    }
+
+    /// Approximate value as a float
+    pub fn to_f64(&self) -> f64 {
+        let value = self.scale.to_f64(self.magnitude);
+        if self.negative {
+            -value
+        } else {
+            value
+        }
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negative {
+            f.write_str("-")?;
+        }
+        if self.scale.decimals == 0 {
+            return write!(f, "{}", self.magnitude);
+        }
+        let factor = self.scale.factor();
+        write!(
+            f,
+            "{}.{:0width$}",
+            self.magnitude / factor,
+            self.magnitude % factor,
+            width = self.scale.decimals as usize
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let scale = DecimalScale::new(2);
+        assert_eq!(scale.parse("50.25"), Ok(5025));
+        assert_eq!(scale.parse("50.2"), Ok(5020));
+        assert_eq!(scale.parse("50"), Ok(5000));
+        assert_eq!(scale.parse("50."), Ok(5000));
+        assert_eq!(scale.parse(".5"), Ok(50));
+        assert_eq!(scale.parse("0.250000"), Ok(25));
+        assert_eq!(scale.parse("007.01"), Ok(701));
+
+        for bad in ["", ".", "50.251", "-1", "+1", "1e3", " 1", "1,5", "1.2.3"] {
+            assert!(scale.parse(bad).is_err(), "{:?} should be rejected", bad);
+        }
+        assert!(scale.parse("184467440737095516.16").is_err());
+        assert_eq!(scale.parse("184467440737095516.15"), Ok(u64::MAX));
+        assert_eq!(DecimalScale::new(0).parse("42"), Ok(42));
+        assert!(DecimalScale::new(0).parse("42.5").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        let scale = DecimalScale::new(2);
+        assert_eq!(scale.decimal(5025).to_string(), "50.25");
+        assert_eq!(scale.decimal(5).to_string(), "0.05");
+        assert_eq!(scale.decimal(5000).to_string(), "50.00");
+        assert_eq!(DecimalScale::new(0).decimal(42).to_string(), "42");
+        assert_eq!(DecimalScale::new(4).decimal(1_000_100).to_string(), "100.0100");
+    }
+
+    #[test]
+    fn test_f64_conversion_is_exact() {
+        let scale = DecimalScale::new(2);
+        assert_eq!(scale.from_f64(50.25), Ok(5025));
+        // 0.1 + 0.2 is 0.30000000000000004 in binary floating point
+        assert!(scale.from_f64(0.1 + 0.2).is_err());
+        assert_eq!(scale.from_f64(0.3), Ok(30));
+        assert!(scale.from_f64(f64::NAN).is_err());
+        assert!(scale.from_f64(-1.0).is_err());
+        assert_eq!(scale.to_f64(5025), 50.25);
+        assert_eq!(Decimal::parse("1.5", scale).unwrap().to_f64(), 1.5);
+    }
+
+    #[test]
+    fn test_signed() {
+        let scale = DecimalScale::new(2);
+        assert_eq!(scale.parse_signed("-1.25"), Ok(-125));
+        assert_eq!(scale.parse_signed("-0.05"), Ok(-5));
+        assert_eq!(scale.parse_signed("3"), Ok(300));
+        assert!(scale.parse_signed("--1").is_err());
+        assert!(scale.parse_signed("-").is_err());
+        assert!(scale.parse_signed("92233720368547758.08").is_err());
+        assert_eq!(scale.parse_signed("-92233720368547758.08"), Ok(i64::MIN));
+
+        assert_eq!(scale.signed_decimal(-125).to_string(), "-1.25");
+        assert_eq!(scale.signed_decimal(-5).to_string(), "-0.05");
+        assert_eq!(scale.signed_decimal(i64::MIN).to_string(), "-92233720368547758.08");
+        assert_eq!(Decimal::parse("-37.63", scale).unwrap().units(), -3763);
+        assert_eq!(Decimal::parse("-37.63", scale).unwrap().to_f64(), -37.63);
+    }
+}
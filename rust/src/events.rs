@@ -0,0 +1,143 @@
+//! Book mutation events for mirroring the orderbook downstream
+//!
+//! When event recording is enabled, every mutation of an `OrderBook` appends
+//! one or more `BookEvent`s to an internal buffer, which consumers collect with
+//! `OrderBook::drain_events`. Order events carry the affected level's size
+//! before and after the change, so consumers can apply exact incremental deltas
+//! without re-reading the book.
+
+use crate::types::{ExchangeId, OrderId, Price, Quantity, Side, Timestamp, TradingPhase};
+
+#[cfg(feature = "serde_support")]
+use serde::{Deserialize, Serialize};
+
+/// Best bid and ask as (price, quantity), if present
+pub type Bbo = (Option<(Price, Quantity)>, Option<(Price, Quantity)>);
+
+/// Size change of a single price level caused by an order event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct LevelDelta {
+    /// Side of the level
+    pub side: Side,
+    /// Price of the level
+    pub price: Price,
+    /// `Limit.size` before the change
+    pub size_before: Quantity,
+    /// `Limit.size` after the change (0 if the level was removed)
+    pub size_after: Quantity,
+}
+
+impl LevelDelta {
+    /// Create a new level delta
+    pub fn new(side: Side, price: Price, size_before: Quantity, size_after: Quantity) -> Self {
+        Self {
+            side,
+            price,
+            size_before,
+            size_after,
+        }
+    }
+}
+
+/// A single mutation of the orderbook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum BookEvent {
+    /// An order was added to a level
+    OrderAdded {
+        order_id: OrderId,
+        quantity: Quantity,
+        entry_time: Timestamp,
+        exchange_id: ExchangeId,
+        level: LevelDelta,
+        timestamp: Timestamp,
+    },
+    /// An order was removed from the book before being filled
+    OrderCancelled {
+        order_id: OrderId,
+        quantity: Quantity,
+        level: LevelDelta,
+        timestamp: Timestamp,
+    },
+    /// An order's price and/or quantity changed
+    ///
+    /// For same-price modifications `old_level` and `new_level` describe the
+    /// same level and are identical.
+    OrderModified {
+        order_id: OrderId,
+        old_quantity: Quantity,
+        new_quantity: Quantity,
+        priority_lost: bool,
+        old_level: LevelDelta,
+        new_level: LevelDelta,
+        timestamp: Timestamp,
+    },
+    /// A resting order was (partially) filled by an incoming order
+    OrderFilled {
+        order_id: OrderId,
+        aggressor_order_id: OrderId,
+        quantity: Quantity,
+        remaining_quantity: Quantity,
+        level: LevelDelta,
+        timestamp: Timestamp,
+    },
+    /// A price level gained its first displayed order
+    ///
+    /// Levels holding only hidden orders are never reported.
+    LevelCreated {
+        side: Side,
+        price: Price,
+        timestamp: Timestamp,
+    },
+    /// A price level lost its last displayed order
+    ///
+    /// Hidden orders may still rest at the price.
+    LevelRemoved {
+        side: Side,
+        price: Price,
+        timestamp: Timestamp,
+    },
+    /// The best bid or ask changed in price or size
+    BboChanged {
+        best_bid: Option<(Price, Quantity)>,
+        best_ask: Option<(Price, Quantity)>,
+        timestamp: Timestamp,
+    },
+    /// The book moved to another trading phase
+    PhaseChanged {
+        from: TradingPhase,
+        to: TradingPhase,
+        timestamp: Timestamp,
+    },
+    /// A parked stop order was triggered and is being submitted
+    StopTriggered {
+        order_id: OrderId,
+        side: Side,
+        stop_price: Price,
+        timestamp: Timestamp,
+    },
+    /// A triggered stop order no longer fits the book's rules and was dropped
+    StopRejected {
+        order_id: OrderId,
+        timestamp: Timestamp,
+    },
+}
+
+impl BookEvent {
+    /// Timestamp at which the event occurred
+    pub fn timestamp(&self) -> Timestamp {
+        match *self {
+            BookEvent::OrderAdded { timestamp, .. }
+            | BookEvent::OrderCancelled { timestamp, .. }
+            | BookEvent::OrderModified { timestamp, .. }
+            | BookEvent::OrderFilled { timestamp, .. }
+            | BookEvent::LevelCreated { timestamp, .. }
+            | BookEvent::LevelRemoved { timestamp, .. }
+            | BookEvent::BboChanged { timestamp, .. }
+            | BookEvent::PhaseChanged { timestamp, .. }
+            | BookEvent::StopTriggered { timestamp, .. }
+            | BookEvent::StopRejected { timestamp, .. } => timestamp,
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Per-instrument trading rules
+//!
+//! An `InstrumentConfig` is attached to every `OrderBook` and checked whenever
+//! an order enters the book or is modified. The default configuration accepts
+//! any positive price and quantity, matching the book's original behaviour.
+//!
+//! The configuration also carries the decimal scales used to convert between
+//! human-readable prices and quantities ("50.25") and integer book units.
+
+use crate::decimal::{Decimal, DecimalScale};
+use crate::types::{Price, Quantity};
+use crate::{OrderBookError, Result};
+
+#[cfg(feature = "serde_support")]
+use serde::{Deserialize, Serialize};
+
+/// Tick size, lot size, quantity limits and price band of an instrument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct InstrumentConfig {
+    /// Prices must be a multiple of this (0 disables the check)
+    pub tick_size: Price,
+    /// Quantities must be a multiple of this (0 disables the check)
+    pub lot_size: Quantity,
+    /// Smallest accepted order quantity
+    pub min_quantity: Quantity,
+    /// Largest accepted order quantity
+    pub max_quantity: Quantity,
+    /// Inclusive range of accepted prices, if any
+    pub price_band: Option<(Price, Price)>,
+    /// Accept zero and negative prices, e.g. for calendar spreads
+    pub allow_negative_prices: bool,
+    /// Decimal places of one price unit
+    pub price_scale: DecimalScale,
+    /// Decimal places of one quantity unit
+    pub quantity_scale: DecimalScale,
+}
+
+impl Default for InstrumentConfig {
+    fn default() -> Self {
+        Self {
+            tick_size: 1,
+            lot_size: 1,
+            min_quantity: 1,
+            max_quantity: Quantity::MAX,
+            price_band: None,
+            allow_negative_prices: false,
+            price_scale: DecimalScale::default(),
+            quantity_scale: DecimalScale::default(),
+        }
+    }
+}
+
+impl InstrumentConfig {
+    /// Create a configuration with the given tick and lot size
+    pub fn new(tick_size: Price, lot_size: Quantity) -> Self {
+        Self {
+            tick_size,
+            lot_size,
+            min_quantity: lot_size,
+            ..Self::default()
+        }
+    }
+
+    /// Set the accepted quantity range
+    pub fn with_quantity_limits(mut self, min_quantity: Quantity, max_quantity: Quantity) -> Self {
+        self.min_quantity = min_quantity;
+        self.max_quantity = max_quantity;
+        self
+    }
+
+    /// Set the accepted price range (inclusive)
+    pub fn with_price_band(mut self, low: Price, high: Price) -> Self {
+        self.price_band = Some((low, high));
+        self
+    }
+
+    /// Accept zero and negative prices
+    ///
+    /// Needed for calendar spreads and for futures that can trade below zero.
+    pub fn with_negative_prices(mut self) -> Self {
+        self.allow_negative_prices = true;
+        self
+    }
+
+    /// Set the decimal places of one price unit and one quantity unit
+    pub fn with_scales(mut self, price_decimals: u32, quantity_decimals: u32) -> Self {
+        self.price_scale = DecimalScale::new(price_decimals);
+        self.quantity_scale = DecimalScale::new(quantity_decimals);
+        self
+    }
+
+    /// Parse a decimal price string into price units
+    pub fn parse_price(&self, s: &str) -> Result<Price> {
+        self.price_scale.parse_signed(s)
+    }
+
+    /// Parse a decimal quantity string into quantity units
+    pub fn parse_quantity(&self, s: &str) -> Result<Quantity> {
+        self.quantity_scale.parse(s)
+    }
+
+    /// Price units as a displayable decimal
+    pub fn price_decimal(&self, price: Price) -> Decimal {
+        self.price_scale.signed_decimal(price)
+    }
+
+    /// Quantity units as a displayable decimal
+    pub fn quantity_decimal(&self, quantity: Quantity) -> Decimal {
+        self.quantity_scale.decimal(quantity)
+    }
+
+    /// Check a limit price against the tick size and price band
+    pub fn validate_price(&self, price: Price) -> Result<()> {
+        if price <= 0 && !self.allow_negative_prices {
+            return Err(OrderBookError::InvalidPrice(price));
+        }
+        if price.checked_rem(self.tick_size).is_some_and(|r| r != 0) {
+            return Err(OrderBookError::InvalidTick(price));
+        }
+        if let Some((low, high)) = self.price_band {
+            if price < low || price > high {
+                return Err(OrderBookError::PriceOutOfBand(price));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check an order quantity against the lot size and quantity limits
+    pub fn validate_quantity(&self, quantity: Quantity) -> Result<()> {
+        if quantity == 0 {
+            return Err(OrderBookError::InvalidQuantity(quantity));
+        }
+        if quantity.checked_rem(self.lot_size).is_some_and(|r| r != 0) {
+            return Err(OrderBookError::InvalidLot(quantity));
+        }
+        if quantity < self.min_quantity || quantity > self.max_quantity {
+            return Err(OrderBookError::QuantityOutOfRange(quantity));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_accepts_positive_values() {
+        let config = InstrumentConfig::default();
+        assert_eq!(config.validate_price(1), Ok(()));
+        assert_eq!(config.validate_price(0), Err(OrderBookError::InvalidPrice(0)));
+        assert_eq!(config.validate_price(-5), Err(OrderBookError::InvalidPrice(-5)));
+        assert_eq!(config.validate_quantity(1), Ok(()));
+        assert_eq!(config.validate_quantity(0), Err(OrderBookError::InvalidQuantity(0)));
+    }
+
+    #[test]
+    fn test_validation() {
+        let config = InstrumentConfig::new(5, 100)
+            .with_quantity_limits(200, 10_000)
+            .with_price_band(9_000, 11_000);
+
+        assert_eq!(config.validate_price(10_005), Ok(()));
+        assert_eq!(config.validate_price(10_003), Err(OrderBookError::InvalidTick(10_003)));
+        assert_eq!(config.validate_price(8_995), Err(OrderBookError::PriceOutOfBand(8_995)));
+        assert_eq!(config.validate_price(11_000), Ok(()));
+
+        assert_eq!(config.validate_quantity(300), Ok(()));
+        assert_eq!(config.validate_quantity(250), Err(OrderBookError::InvalidLot(250)));
+        assert_eq!(config.validate_quantity(100), Err(OrderBookError::QuantityOutOfRange(100)));
+        assert_eq!(config.validate_quantity(10_100), Err(OrderBookError::QuantityOutOfRange(10_100)));
+    }
+
+    #[test]
+    fn test_negative_prices() {
+        let config = InstrumentConfig::new(5, 1)
+            .with_negative_prices()
+            .with_price_band(-1_000, 1_000);
+
+        assert_eq!(config.validate_price(-995), Ok(()));
+        assert_eq!(config.validate_price(0), Ok(()));
+        assert_eq!(config.validate_price(-3), Err(OrderBookError::InvalidTick(-3)));
+        assert_eq!(config.validate_price(-1_005), Err(OrderBookError::PriceOutOfBand(-1_005)));
+    }
+}
@@ -0,0 +1,568 @@
+//! NASDAQ TotalView-ITCH 5.0 feed handler
+//!
+//! Parses the order-level subset of ITCH 5.0 needed to rebuild a venue book and
+//! applies it to one `OrderBook` per stock locate:
+//!
+//! | Type | Message                       | Book operation                        |
+//! |------|-------------------------------|---------------------------------------|
+//! | `A`  | Add Order                     | `add_order`                           |
+//! | `F`  | Add Order with MPID           | `add_order`                           |
+//! | `E`  | Order Executed                | `update_order`, or `remove_order`     |
+//! | `C`  | Order Executed with Price     | `update_order`, or `remove_order`     |
+//! | `X`  | Order Cancel                  | `update_order`, or `remove_order`     |
+//! | `D`  | Order Delete                  | `remove_order`                        |
+//! | `U`  | Order Replace                 | `remove_order` then `add_order`       |
+//!
+//! All other message types are skipped. Messages are big-endian; prices keep
+//! ITCH's four implied decimal places (so `Price` is in units of 1/10000) and
+//! timestamps are nanoseconds since midnight. Order reference numbers are used
+//! as order IDs.
+//!
+//! `ItchReader` reads the length-prefixed framing used by NASDAQ's binary files
+//! (and SoupBinTCP payloads): each message is preceded by its length as a
+//! big-endian `u16`.
+
+use crate::order::Order;
+use crate::orderbook::OrderBook;
+use crate::types::{ExchangeId, OrderId, Price, Quantity, Side, Timestamp};
+use crate::{OrderBookError, Result};
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+/// ITCH stock locate code identifying an instrument within a session
+pub type StockLocate = u16;
+
+/// Length of the common message header (type, locate, tracking number, timestamp)
+const HEADER_LEN: usize = 11;
+
+/// A parsed ITCH message from the supported subset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItchMessage {
+    /// `A` and `F`: a new order was accepted and added to the book
+    AddOrder {
+        locate: StockLocate,
+        timestamp: Timestamp,
+        order_id: OrderId,
+        side: Side,
+        shares: Quantity,
+        stock: [u8; 8],
+        price: Price,
+        /// Market participant ID (`F` messages only)
+        mpid: Option<[u8; 4]>,
+    },
+    /// `E` and `C`: part or all of an order was executed
+    OrderExecuted {
+        locate: StockLocate,
+        timestamp: Timestamp,
+        order_id: OrderId,
+        shares: Quantity,
+        match_number: u64,
+        /// Execution price if different from the order's price (`C` messages only)
+        price: Option<Price>,
+    },
+    /// `X`: part of an order was cancelled
+    OrderCancel {
+        locate: StockLocate,
+        timestamp: Timestamp,
+        order_id: OrderId,
+        shares: Quantity,
+    },
+    /// `D`: an order was removed from the book
+    OrderDelete {
+        locate: StockLocate,
+        timestamp: Timestamp,
+        order_id: OrderId,
+    },
+    /// `U`: an order was cancelled and replaced by a new order
+    OrderReplace {
+        locate: StockLocate,
+        timestamp: Timestamp,
+        original_order_id: OrderId,
+        new_order_id: OrderId,
+        shares: Quantity,
+        price: Price,
+    },
+}
+
+impl ItchMessage {
+    /// Parse a single message (without length prefix)
+    ///
+    /// Returns `Ok(None)` for message types outside the supported subset.
+    pub fn parse(buf: &[u8]) -> Result<Option<ItchMessage>> {
+        let Some(&message_type) = buf.first() else {
+            return Err(feed_error("empty message"));
+        };
+        let expected_len = match message_type {
+            b'A' => 36,
+            b'F' => 40,
+            b'E' => 31,
+            b'C' => 36,
+            b'X' => 23,
+            b'D' => 19,
+            b'U' => 35,
+            _ => return Ok(None),
+        };
+        if buf.len() < expected_len {
+            return Err(feed_error(format!(
+                "'{}' message is {} bytes, expected {}",
+                message_type as char,
+                buf.len(),
+                expected_len
+            )));
+        }
+
+        let locate = be_u16(&buf[1..3]);
+        let timestamp = be_u48(&buf[5..11]);
+        let body = &buf[HEADER_LEN..];
+
+        let message = match message_type {
+            b'A' | b'F' => ItchMessage::AddOrder {
+                locate,
+                timestamp,
+                order_id: be_u64(&body[0..8]),
+                side: match body[8] {
+                    b'B' => Side::Buy,
+                    b'S' => Side::Sell,
+                    other => {
+                        return Err(feed_error(format!("invalid buy/sell indicator {:?}", other as char)))
+                    }
+                },
+                shares: be_u32(&body[9..13]) as Quantity,
+                stock: body[13..21].try_into().unwrap(),
+                price: be_u32(&body[21..25]) as Price,
+                mpid: (message_type == b'F').then(|| body[25..29].try_into().unwrap()),
+            },
+            b'E' | b'C' => ItchMessage::OrderExecuted {
+                locate,
+                timestamp,
+                order_id: be_u64(&body[0..8]),
+                shares: be_u32(&body[8..12]) as Quantity,
+                match_number: be_u64(&body[12..20]),
+                price: (message_type == b'C').then(|| be_u32(&body[21..25]) as Price),
+            },
+            b'X' => ItchMessage::OrderCancel {
+                locate,
+                timestamp,
+                order_id: be_u64(&body[0..8]),
+                shares: be_u32(&body[8..12]) as Quantity,
+            },
+            b'D' => ItchMessage::OrderDelete {
+                locate,
+                timestamp,
+                order_id: be_u64(&body[0..8]),
+            },
+            b'U' => ItchMessage::OrderReplace {
+                locate,
+                timestamp,
+                original_order_id: be_u64(&body[0..8]),
+                new_order_id: be_u64(&body[8..16]),
+                shares: be_u32(&body[16..20]) as Quantity,
+                price: be_u32(&body[20..24]) as Price,
+            },
+            _ => unreachable!(),
+        };
+
+        Ok(Some(message))
+    }
+
+    /// Stock locate of the instrument the message refers to
+    pub fn locate(&self) -> StockLocate {
+        match *self {
+            ItchMessage::AddOrder { locate, .. }
+            | ItchMessage::OrderExecuted { locate, .. }
+            | ItchMessage::OrderCancel { locate, .. }
+            | ItchMessage::OrderDelete { locate, .. }
+            | ItchMessage::OrderReplace { locate, .. } => locate,
+        }
+    }
+
+    /// Nanoseconds since midnight at which the message was generated
+    pub fn timestamp(&self) -> Timestamp {
+        match *self {
+            ItchMessage::AddOrder { timestamp, .. }
+            | ItchMessage::OrderExecuted { timestamp, .. }
+            | ItchMessage::OrderCancel { timestamp, .. }
+            | ItchMessage::OrderDelete { timestamp, .. }
+            | ItchMessage::OrderReplace { timestamp, .. } => timestamp,
+        }
+    }
+}
+
+/// Reads length-prefixed ITCH messages, skipping unsupported types
+#[derive(Debug)]
+pub struct ItchReader<R: Read> {
+    reader: R,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl<R: Read> ItchReader<R> {
+    /// Read messages from `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::with_capacity(64),
+            done: false,
+        }
+    }
+
+    /// Read the next framed message, or `None` at a clean end of stream
+    fn read_frame(&mut self) -> Result<Option<&[u8]>> {
+        let mut len = [0u8; 2];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(feed_error(e)),
+        }
+
+        self.buf.resize(u16::from_be_bytes(len) as usize, 0);
+        self.reader.read_exact(&mut self.buf).map_err(feed_error)?;
+        Ok(Some(&self.buf))
+    }
+}
+
+impl<R: Read> Iterator for ItchReader<R> {
+    type Item = Result<ItchMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let result = self.read_frame().and_then(|frame| match frame {
+                Some(frame) => ItchMessage::parse(frame).map(Some),
+                None => Ok(None),
+            });
+
+            match result {
+                Ok(Some(Some(message))) => return Some(Ok(message)),
+                Ok(Some(None)) => continue,
+                Ok(None) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Maintains one `OrderBook` per stock locate from ITCH messages
+#[derive(Debug, Default)]
+pub struct ItchHandler {
+    books: HashMap<StockLocate, OrderBook>,
+    /// Exchange ID assigned to every order added from the feed
+    exchange_id: ExchangeId,
+}
+
+impl ItchHandler {
+    /// Create a handler whose orders carry `exchange_id`
+    pub fn new(exchange_id: ExchangeId) -> Self {
+        Self {
+            books: HashMap::new(),
+            exchange_id,
+        }
+    }
+
+    /// Get the book for a stock locate
+    pub fn book(&self, locate: StockLocate) -> Option<&OrderBook> {
+        self.books.get(&locate)
+    }
+
+    /// Iterate over all books and their stock locates
+    pub fn books(&self) -> impl Iterator<Item = (StockLocate, &OrderBook)> {
+        self.books.iter().map(|(&locate, book)| (locate, book))
+    }
+
+    /// Read and apply every message from a length-prefixed stream
+    ///
+    /// Returns the number of supported messages applied.
+    pub fn process<R: Read>(&mut self, reader: R) -> Result<usize> {
+        let mut applied = 0;
+        for message in ItchReader::new(reader) {
+            self.apply(&message?)?;
+            applied += 1;
+        }
+        Ok(applied)
+    }
+
+    /// Apply a single message to its stock's book
+    pub fn apply(&mut self, message: &ItchMessage) -> Result<()> {
+        let book = self.books.entry(message.locate()).or_default();
+        book.set_time(message.timestamp());
+
+        match *message {
+            ItchMessage::AddOrder { timestamp, order_id, side, shares, price, .. } => {
+                book.add_order(Order::new(order_id, side, shares, price, timestamp, self.exchange_id))
+            }
+            ItchMessage::OrderExecuted { order_id, shares, .. }
+            | ItchMessage::OrderCancel { order_id, shares, .. } => {
+                Self::reduce_order(book, order_id, shares)
+            }
+            ItchMessage::OrderDelete { order_id, .. } => book.remove_order(order_id).map(|_| ()),
+            ItchMessage::OrderReplace {
+                timestamp,
+                original_order_id,
+                new_order_id,
+                shares,
+                price,
+                ..
+            } => {
+                let side = book
+                    .get_order(original_order_id)
+                    .ok_or(OrderBookError::OrderNotFound(original_order_id))?
+                    .side;
+                let replacement = Order::new(new_order_id, side, shares, price, timestamp, self.exchange_id);
+
+                // Check the replacement first, so a rejected replace leaves the
+                // original order resting
+                book.validate_resting_order(&replacement)?;
+                if new_order_id != original_order_id && book.contains_order(new_order_id) {
+                    return Err(OrderBookError::OrderAlreadyExists(new_order_id));
+                }
+                book.remove_order(original_order_id)?;
+                book.add_order(replacement)
+            }
+        }
+    }
+
+    /// Take executed or cancelled shares off an order, removing it when none are left
+    fn reduce_order(book: &mut OrderBook, order_id: OrderId, shares: Quantity) -> Result<()> {
+        let open = book
+            .get_order(order_id)
+            .ok_or(OrderBookError::OrderNotFound(order_id))?
+            .quantity;
+        if shares >= open {
+            book.remove_order(order_id).map(|_| ())
+        } else {
+            book.update_order(order_id, open - shares)
+        }
+    }
+}
+
+fn feed_error(msg: impl ToString) -> OrderBookError {
+    OrderBookError::FeedError(msg.to_string())
+}
+
+fn be_u16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes(bytes.try_into().unwrap())
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes.try_into().unwrap())
+}
+
+fn be_u48(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u64)
+}
+
+fn be_u64(bytes: &[u8]) -> u64 {
+    u64::from_be_bytes(bytes.try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Builds synthetic length-prefixed ITCH streams
+    #[derive(Default)]
+    struct ItchWriter {
+        bytes: Vec<u8>,
+    }
+
+    impl ItchWriter {
+        fn message(&mut self, message_type: u8, locate: u16, timestamp: u64, body: &[u8]) -> &mut Self {
+            let len = (HEADER_LEN + body.len()) as u16;
+            self.bytes.extend_from_slice(&len.to_be_bytes());
+            self.bytes.push(message_type);
+            self.bytes.extend_from_slice(&locate.to_be_bytes());
+            self.bytes.extend_from_slice(&0u16.to_be_bytes());
+            self.bytes.extend_from_slice(&timestamp.to_be_bytes()[2..]);
+            self.bytes.extend_from_slice(body);
+            self
+        }
+
+        fn add(&mut self, locate: u16, ts: u64, id: u64, side: u8, shares: u32, price: u32) -> &mut Self {
+            let mut body = Vec::new();
+            body.extend_from_slice(&id.to_be_bytes());
+            body.push(side);
+            body.extend_from_slice(&shares.to_be_bytes());
+            body.extend_from_slice(b"AAPL    ");
+            body.extend_from_slice(&price.to_be_bytes());
+            self.message(b'A', locate, ts, &body)
+        }
+
+        fn add_mpid(&mut self, locate: u16, ts: u64, id: u64, side: u8, shares: u32, price: u32) -> &mut Self {
+            let mut body = Vec::new();
+            body.extend_from_slice(&id.to_be_bytes());
+            body.push(side);
+            body.extend_from_slice(&shares.to_be_bytes());
+            body.extend_from_slice(b"MSFT    ");
+            body.extend_from_slice(&price.to_be_bytes());
+            body.extend_from_slice(b"GSCO");
+            self.message(b'F', locate, ts, &body)
+        }
+
+        fn executed(&mut self, locate: u16, ts: u64, id: u64, shares: u32) -> &mut Self {
+            let mut body = Vec::new();
+            body.extend_from_slice(&id.to_be_bytes());
+            body.extend_from_slice(&shares.to_be_bytes());
+            body.extend_from_slice(&77u64.to_be_bytes());
+            self.message(b'E', locate, ts, &body)
+        }
+
+        fn executed_with_price(&mut self, locate: u16, ts: u64, id: u64, shares: u32, price: u32) -> &mut Self {
+            let mut body = Vec::new();
+            body.extend_from_slice(&id.to_be_bytes());
+            body.extend_from_slice(&shares.to_be_bytes());
+            body.extend_from_slice(&78u64.to_be_bytes());
+            body.push(b'Y');
+            body.extend_from_slice(&price.to_be_bytes());
+            self.message(b'C', locate, ts, &body)
+        }
+
+        fn cancel(&mut self, locate: u16, ts: u64, id: u64, shares: u32) -> &mut Self {
+            let mut body = Vec::new();
+            body.extend_from_slice(&id.to_be_bytes());
+            body.extend_from_slice(&shares.to_be_bytes());
+            self.message(b'X', locate, ts, &body)
+        }
+
+        fn delete(&mut self, locate: u16, ts: u64, id: u64) -> &mut Self {
+            self.message(b'D', locate, ts, &id.to_be_bytes())
+        }
+
+        fn replace(&mut self, locate: u16, ts: u64, old: u64, new: u64, shares: u32, price: u32) -> &mut Self {
+            let mut body = Vec::new();
+            body.extend_from_slice(&old.to_be_bytes());
+            body.extend_from_slice(&new.to_be_bytes());
+            body.extend_from_slice(&shares.to_be_bytes());
+            body.extend_from_slice(&price.to_be_bytes());
+            self.message(b'U', locate, ts, &body)
+        }
+
+        fn system_event(&mut self, ts: u64) -> &mut Self {
+            self.message(b'S', 0, ts, b"O")
+        }
+    }
+
+    #[test]
+    fn test_parse_add_order_with_mpid() {
+        let mut writer = ItchWriter::default();
+        writer.add_mpid(7, 34_200_000_000_123, 42, b'S', 300, 1_502_500);
+
+        let message = ItchMessage::parse(&writer.bytes[2..]).unwrap().unwrap();
+        assert_eq!(message, ItchMessage::AddOrder {
+            locate: 7,
+            timestamp: 34_200_000_000_123,
+            order_id: 42,
+            side: Side::Sell,
+            shares: 300,
+            stock: *b"MSFT    ",
+            price: 1_502_500,
+            mpid: Some(*b"GSCO"),
+        });
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(ItchMessage::parse(&[]).is_err());
+        assert_eq!(ItchMessage::parse(b"S\0\0\0\0\0\0\0\0\0\0O").unwrap(), None);
+
+        let mut writer = ItchWriter::default();
+        writer.delete(1, 0, 5);
+        assert!(matches!(
+            ItchMessage::parse(&writer.bytes[2..writer.bytes.len() - 1]),
+            Err(OrderBookError::FeedError(_))
+        ));
+    }
+
+    #[test]
+    fn test_handler_applies_messages() {
+        let mut writer = ItchWriter::default();
+        writer
+            .system_event(1)
+            .add(1, 10, 1, b'B', 100, 1_000_000)
+            .add(1, 11, 2, b'B', 200, 1_000_000)
+            .add_mpid(1, 12, 3, b'S', 300, 1_001_000)
+            .add(2, 13, 4, b'S', 50, 2_500_000)
+            .executed(1, 20, 1, 40)
+            .executed_with_price(1, 21, 3, 300, 1_000_900)
+            .cancel(1, 22, 2, 50)
+            .replace(1, 23, 1, 5, 70, 1_000_100)
+            .delete(2, 24, 4);
+
+        let mut handler = ItchHandler::new(9);
+        assert_eq!(handler.process(writer.bytes.as_slice()).unwrap(), 9);
+
+        let book = handler.book(1).unwrap();
+        assert_eq!(book.current_time(), 23);
+        assert_eq!(book.best_bid(), Some((1_000_100, 70)));
+        assert_eq!(book.best_ask(), None);
+        assert_eq!(book.volume_at_price(1_000_000), Some(150));
+        assert!(!book.contains_order(1));
+        let replaced = book.get_order(5).unwrap();
+        assert_eq!((replaced.side, replaced.entry_time, replaced.exchange_id), (Side::Buy, 23, 9));
+
+        let book = handler.book(2).unwrap();
+        assert_eq!(book.total_orders(), 0);
+        assert_eq!(handler.books().count(), 2);
+    }
+
+    #[test]
+    fn test_unknown_order_is_an_error() {
+        let mut writer = ItchWriter::default();
+        writer.executed(1, 10, 99, 1);
+        let mut handler = ItchHandler::new(0);
+        assert_eq!(
+            handler.process(writer.bytes.as_slice()).unwrap_err(),
+            OrderBookError::OrderNotFound(99)
+        );
+    }
+
+    #[test]
+    fn test_rejected_replace_keeps_original() {
+        let mut writer = ItchWriter::default();
+        writer
+            .add(1, 10, 1, b'B', 100, 1_000_000)
+            .add(1, 11, 2, b'B', 200, 1_000_000)
+            .replace(1, 12, 1, 2, 70, 1_000_100)
+            .replace(1, 13, 1, 3, 0, 1_000_100);
+
+        let mut handler = ItchHandler::new(0);
+        let mut messages = ItchReader::new(writer.bytes.as_slice()).map(|m| m.unwrap());
+        for message in messages.by_ref().take(2) {
+            handler.apply(&message).unwrap();
+        }
+        assert_eq!(handler.apply(&messages.next().unwrap()), Err(OrderBookError::OrderAlreadyExists(2)));
+        assert_eq!(handler.apply(&messages.next().unwrap()), Err(OrderBookError::InvalidQuantity(0)));
+
+        let book = handler.book(1).unwrap();
+        assert_eq!(book.orders_at_level(Side::Buy, 1_000_000).map(|o| o.id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(book.get_order(1).unwrap().quantity, 100);
+    }
+
+    #[test]
+    fn test_process_binary_file() {
+        let mut writer = ItchWriter::default();
+        for i in 0..100u64 {
+            let side = if i % 2 == 0 { b'B' } else { b'S' };
+            let price = if i % 2 == 0 { 990_000 - i as u32 * 100 } else { 1_010_000 + i as u32 * 100 };
+            writer.add(3, i, i + 1, side, 10, price);
+        }
+        for i in 0..50u64 {
+            writer.delete(3, 100 + i, 2 * i + 1);
+        }
+
+        let path = std::env::temp_dir().join(format!("itch_test_{}.bin", std::process::id()));
+        std::fs::File::create(&path).unwrap().write_all(&writer.bytes).unwrap();
+        let mut handler = ItchHandler::new(1);
+        let applied = handler.process(std::io::BufReader::new(std::fs::File::open(&path).unwrap()));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(applied.unwrap(), 150);
+        let book = handler.book(3).unwrap();
+        assert_eq!(book.total_orders(), 50);
+        assert_eq!(book.best_bid(), None);
+        assert_eq!(book.best_ask(), Some((1_010_100, 10)));
+    }
+}
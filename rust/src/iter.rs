@@ -0,0 +1,71 @@
+//! Borrowing iterators over the orderbook's internal arenas
+
+use crate::avl_tree::AvlIndex;
+use crate::level_index::PriceLevelIndex;
+use crate::limit::Limit;
+use crate::order::Order;
+use crate::orderbook::OrderBook;
+
+/// Iterator over the orders resting at a single price level, in FIFO order
+///
+/// Follows the `next_order_index` links from the limit's head order, so the
+/// first item is the order that would be filled first.
+#[derive(Debug, Clone)]
+pub struct LevelOrders<'a> {
+    orders: &'a [Option<Order>],
+    next: Option<usize>,
+}
+
+impl<'a> LevelOrders<'a> {
+    /// Create an iterator starting at the given head order index
+    pub(crate) fn new(orders: &'a [Option<Order>], head: Option<usize>) -> Self {
+        Self { orders, next: head }
+    }
+}
+
+impl<'a> Iterator for LevelOrders<'a> {
+    type Item = &'a Order;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let order = self.orders[self.next?].as_ref()?;
+        self.next = order.next_order_index;
+        Some(order)
+    }
+}
+
+/// Iterator over one side's price levels, from the best price outwards
+///
+/// Starts at the cached best level and follows each `Limit`'s link to its
+/// neighbouring level. Nothing is collected or sorted up front, so taking the
+/// top N levels is N pointer hops, plus any levels holding only hidden orders
+/// that are skipped over.
+#[derive(Debug, Clone)]
+pub struct Levels<'a, I: PriceLevelIndex = AvlIndex> {
+    book: &'a OrderBook<I>,
+    next: Option<usize>,
+    include_hidden: bool,
+}
+
+impl<'a, I: PriceLevelIndex> Levels<'a, I> {
+    /// Create an iterator starting at the given best limit index
+    ///
+    /// Levels with no displayed quantity are skipped unless `include_hidden`.
+    pub(crate) fn new(book: &'a OrderBook<I>, best: Option<usize>, include_hidden: bool) -> Self {
+        Self { book, next: best, include_hidden }
+    }
+}
+
+impl<'a, I: PriceLevelIndex> Iterator for Levels<'a, I> {
+    type Item = &'a Limit;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let limit_idx = self.next?;
+            self.next = self.book.next_worse_level(limit_idx);
+            let limit = self.book.limits[limit_idx].as_ref()?;
+            if self.include_hidden || limit.size > 0 {
+                return Some(limit);
+            }
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//! Dense price ladder for instruments trading in a narrow tick range
+//!
+//! A `LadderIndex` keeps one slot per tick over a window of prices, so finding
+//! the level at a price is a subtraction and a division instead of a hash or
+//! tree lookup. A bitset records which slots hold a level; best-price and
+//! next-level searches scan it a 64-slot word at a time using find-first-set
+//! (`trailing_zeros` / `leading_zeros`).
+//!
+//! The window follows the market: when a level arrives outside it, the ladder
+//! re-centers on the occupied range and grows if needed, up to `max_slots`.
+//! Slots hold indices into the book's `Limit` arena, so the queues themselves
+//! are shared with the other backends.
+
+use crate::instrument::InstrumentConfig;
+use crate::level_index::PriceLevelIndex;
+use crate::types::Price;
+
+/// Bits per bitset word
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Dense price ladder with one slot per tick and a bitset of occupied slots
+#[derive(Debug, Clone)]
+pub struct LadderIndex {
+    /// Price of slot 0, always a multiple of `tick`
+    base: Price,
+    /// Price distance between adjacent slots
+    tick: Price,
+    /// Limit index resting at each slot's price
+    slots: Vec<Option<usize>>,
+    /// One bit per slot, set when the slot holds a level
+    occupied: Vec<u64>,
+    /// Number of occupied slots
+    len: usize,
+    /// Largest window the ladder may grow to
+    max_slots: usize,
+}
+
+impl LadderIndex {
+    /// Window size used when the instrument has no usable price band
+    pub const DEFAULT_SLOTS: usize = 4096;
+    /// Default limit on the window size (16 MiB of slots)
+    pub const MAX_SLOTS: usize = 1 << 20;
+
+    /// Create a ladder whose initial window covers `low..=high` in steps of `tick`
+    ///
+    /// The first slot is the lowest multiple of `tick` not below `low`.
+    /// A `tick` of 0 is treated as 1.
+    pub fn new(low: Price, high: Price, tick: Price) -> Self {
+        let tick = tick.max(1);
+        let base = (low as i128).div_euclid(tick as i128) * tick as i128;
+        let base = if base < low as i128 { base + tick as i128 } else { base };
+        let slots = if (high as i128) < base {
+            0
+        } else {
+            ((high as i128 - base) / tick as i128 + 1) as usize
+        };
+
+        let mut ladder = Self {
+            base: base as Price,
+            tick,
+            slots: Vec::new(),
+            occupied: Vec::new(),
+            len: 0,
+            max_slots: Self::MAX_SLOTS.max(slots),
+        };
+        ladder.resize_window(slots);
+        ladder
+    }
+
+    /// Limit how many slots the window may grow to when re-centering
+    pub fn with_max_slots(mut self, max_slots: usize) -> Self {
+        self.max_slots = max_slots.max(self.slots.len());
+        self
+    }
+
+    /// Lowest and highest price of the current window
+    pub fn range(&self) -> Option<(Price, Price)> {
+        let last = self.slots.len().checked_sub(1)?;
+        Some((self.base, self.slot_price(last)))
+    }
+
+    /// Replace the window with `slots` empty slots starting at `base`
+    fn resize_window(&mut self, slots: usize) {
+        self.slots = vec![None; slots];
+        self.occupied = vec![0; slots.div_ceil(WORD_BITS)];
+        self.len = 0;
+    }
+
+    /// Slot holding `price`, if it lies in the window and on the tick grid
+    fn slot(&self, price: Price) -> Option<usize> {
+        let offset = price as i128 - self.base as i128;
+        if offset < 0 || offset % self.tick as i128 != 0 {
+            return None;
+        }
+        let slot = offset / self.tick as i128;
+        (slot < self.slots.len() as i128).then_some(slot as usize)
+    }
+
+    /// Price of a slot
+    fn slot_price(&self, slot: usize) -> Price {
+        self.base + slot as Price * self.tick
+    }
+
+    /// Number of slots priced strictly below `price`
+    fn slots_below(&self, price: Price) -> usize {
+        let offset = price as i128 - self.base as i128;
+        if offset <= 0 {
+            return 0;
+        }
+        let below = (offset + self.tick as i128 - 1) / self.tick as i128;
+        below.min(self.slots.len() as i128) as usize
+    }
+
+    /// Number of slots priced at or below `price`
+    fn slots_through(&self, price: Price) -> usize {
+        let offset = price as i128 - self.base as i128;
+        if offset < 0 {
+            return 0;
+        }
+        (offset / self.tick as i128 + 1).min(self.slots.len() as i128) as usize
+    }
+
+    /// First occupied slot at or after `start`
+    fn first_set_from(&self, start: usize) -> Option<usize> {
+        let mut word_idx = start / WORD_BITS;
+        let mut word = *self.occupied.get(word_idx)? & (u64::MAX << (start % WORD_BITS));
+        loop {
+            if word != 0 {
+                return Some(word_idx * WORD_BITS + word.trailing_zeros() as usize);
+            }
+            word_idx += 1;
+            word = *self.occupied.get(word_idx)?;
+        }
+    }
+
+    /// Last occupied slot before `end`
+    fn last_set_before(&self, end: usize) -> Option<usize> {
+        let last = end.checked_sub(1)?;
+        let mut word_idx = last / WORD_BITS;
+        let mut word = self.occupied[word_idx] & (u64::MAX >> (WORD_BITS - 1 - last % WORD_BITS));
+        loop {
+            if word != 0 {
+                return Some(word_idx * WORD_BITS + WORD_BITS - 1 - word.leading_zeros() as usize);
+            }
+            word_idx = word_idx.checked_sub(1)?;
+            word = self.occupied[word_idx];
+        }
+    }
+
+    /// Lowest and highest occupied price, widened to include `price`
+    fn span_with(&self, price: Price) -> (Price, Price) {
+        match (self.first_set_from(0), self.last_set_before(self.slots.len())) {
+            (Some(first), Some(last)) => (price.min(self.slot_price(first)), price.max(self.slot_price(last))),
+            _ => (price, price),
+        }
+    }
+
+    /// Slots needed to cover `low..=high`
+    fn slots_spanning(&self, low: Price, high: Price) -> u128 {
+        ((high as i128 - low as i128) / self.tick as i128) as u128 + 1
+    }
+
+    /// Move the window so that it covers every level and `price`
+    ///
+    /// The new window is centered on the occupied range, at least as large as
+    /// the old one and twice the occupied span where `max_slots` allows, so a
+    /// steady drift re-centers rarely.
+    fn recenter(&mut self, price: Price) {
+        let (low, high) = self.span_with(price);
+        let needed = self.slots_spanning(low, high) as usize;
+        let size = (needed * 2)
+            .max(self.slots.len())
+            .max(Self::DEFAULT_SLOTS)
+            .min(self.max_slots)
+            .max(needed);
+
+        // Center the occupied range, keeping the window inside the i64 range
+        let tick = self.tick as i128;
+        let min_base = (Price::MIN as i128).div_euclid(tick) * tick + tick;
+        let max_base = (Price::MAX as i128 - (size as i128 - 1) * tick).div_euclid(tick) * tick;
+        let spare = (size - needed) as i128 / 2;
+        let base = (low as i128 - spare * tick).clamp(min_base, max_base.max(min_base));
+
+        let mut levels = Vec::with_capacity(self.len);
+        let mut next = self.first_set_from(0);
+        while let Some(slot) = next {
+            levels.push((self.slot_price(slot), self.slots[slot].unwrap()));
+            next = self.first_set_from(slot + 1);
+        }
+        self.base = base as Price;
+        self.resize_window(size);
+        for (level_price, limit_idx) in levels {
+            self.insert(level_price, limit_idx);
+        }
+    }
+}
+
+impl PriceLevelIndex for LadderIndex {
+    /// Covers the instrument's price band when it has one that fits in
+    /// `MAX_SLOTS`; otherwise the window is placed around the first level.
+    fn for_config(config: &InstrumentConfig) -> Self {
+        let ladder = Self::new(0, -1, config.tick_size);
+        match config.price_band {
+            Some((low, high)) if ladder.slots_spanning(low, high) <= Self::MAX_SLOTS as u128 => {
+                Self::new(low, high, config.tick_size)
+            }
+            _ => ladder,
+        }
+    }
+
+    /// Prices off the tick grid, or too far from the resting levels to fit
+    /// within `max_slots`, are not accepted.
+    fn accepts(&self, price: Price) -> bool {
+        if price.rem_euclid(self.tick) != 0 {
+            return false;
+        }
+        if self.slot(price).is_some() {
+            return true;
+        }
+        let (low, high) = self.span_with(price);
+        self.slots_spanning(low, high) <= self.max_slots as u128
+    }
+
+    fn get(&self, price: Price) -> Option<usize> {
+        self.slots[self.slot(price)?]
+    }
+
+    fn insert(&mut self, price: Price, limit_idx: usize) {
+        let slot = match self.slot(price) {
+            Some(slot) => slot,
+            None => {
+                assert!(self.accepts(price), "price {} cannot be held by the ladder", price);
+                self.recenter(price);
+                self.slot(price).expect("re-centered window covers the price")
+            }
+        };
+        debug_assert!(self.slots[slot].is_none(), "level {} already indexed", price);
+        self.slots[slot] = Some(limit_idx);
+        self.occupied[slot / WORD_BITS] |= 1 << (slot % WORD_BITS);
+        self.len += 1;
+    }
+
+    fn remove(&mut self, price: Price) -> Option<usize> {
+        let slot = self.slot(price)?;
+        let removed = self.slots[slot].take();
+        if removed.is_some() {
+            self.occupied[slot / WORD_BITS] &= !(1 << (slot % WORD_BITS));
+            self.len -= 1;
+        }
+        removed
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn lowest(&self) -> Option<usize> {
+        self.first_set_from(0).and_then(|slot| self.slots[slot])
+    }
+
+    fn highest(&self) -> Option<usize> {
+        self.last_set_before(self.slots.len()).and_then(|slot| self.slots[slot])
+    }
+
+    fn next_higher(&self, price: Price) -> Option<usize> {
+        self.first_set_from(self.slots_through(price)).and_then(|slot| self.slots[slot])
+    }
+
+    fn next_lower(&self, price: Price) -> Option<usize> {
+        self.last_set_before(self.slots_below(price)).and_then(|slot| self.slots[slot])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Order, OrderBook, Side};
+    use std::collections::BTreeMap;
+
+    /// Walk the ladder from the lowest level up
+    fn prices(ladder: &LadderIndex, limits: &BTreeMap<usize, Price>) -> Vec<Price> {
+        let mut prices = Vec::new();
+        let mut next = ladder.lowest();
+        while let Some(idx) = next {
+            prices.push(limits[&idx]);
+            next = ladder.next_higher(limits[&idx]);
+        }
+        prices
+    }
+
+    #[test]
+    fn test_window() {
+        let ladder = LadderIndex::new(-12, 23, 5);
+        assert_eq!(ladder.range(), Some((-10, 20)));
+        assert!(ladder.accepts(-10));
+        assert!(ladder.accepts(0));
+        assert!(!ladder.accepts(3));
+        // Outside the window but on the grid: the ladder re-centers
+        assert!(ladder.accepts(-15));
+        assert!(ladder.accepts(25));
+
+        let unbounded = LadderIndex::for_config(&InstrumentConfig::default());
+        assert_eq!(unbounded.range(), None);
+        assert!(unbounded.accepts(100));
+
+        let banded = LadderIndex::for_config(&InstrumentConfig::new(5, 1).with_price_band(9_000, 11_000));
+        assert_eq!(banded.range(), Some((9_000, 11_000)));
+    }
+
+    #[test]
+    fn test_bitset_search_crosses_words() {
+        let mut ladder = LadderIndex::new(0, 1_000, 1);
+        let mut limits = BTreeMap::new();
+        for (idx, price) in [0, 63, 64, 65, 127, 128, 700, 1_000].into_iter().enumerate() {
+            ladder.insert(price, idx);
+            limits.insert(idx, price);
+        }
+
+        assert_eq!(prices(&ladder, &limits), vec![0, 63, 64, 65, 127, 128, 700, 1_000]);
+        assert_eq!(ladder.highest(), Some(7));
+        assert_eq!(ladder.next_lower(700), Some(5));
+        assert_eq!(ladder.next_lower(64), Some(1));
+        assert_eq!(ladder.next_lower(0), None);
+        assert_eq!(ladder.next_higher(128), Some(6));
+        assert_eq!(ladder.next_higher(1_000), None);
+
+        assert_eq!(ladder.remove(64), Some(2));
+        assert_eq!(ladder.remove(64), None);
+        assert_eq!(ladder.next_higher(63), Some(3));
+        assert_eq!(ladder.len(), 7);
+    }
+
+    #[test]
+    fn test_recenters_on_drift() {
+        let mut ladder = LadderIndex::new(1_000, 1_015, 1);
+        let mut limits = BTreeMap::new();
+
+        // A market drifting upwards, leaving a level behind at the start
+        ladder.insert(1_000, 0);
+        limits.insert(0, 1_000);
+        for step in 1..200 {
+            let price = 1_000 + step * 37;
+            ladder.insert(price, step as usize);
+            limits.insert(step as usize, price);
+        }
+        let (low, high) = ladder.range().unwrap();
+        assert!(low <= 1_000 && high >= 1_000 + 199 * 37);
+
+        let expected: Vec<Price> = (0..200).map(|step| 1_000 + step * 37).collect();
+        assert_eq!(prices(&ladder, &limits), expected);
+        assert_eq!(ladder.len(), 200);
+
+        // Once the old levels are gone the window can move freely again
+        for step in 0..200 {
+            ladder.remove(1_000 + step * 37);
+        }
+        ladder.insert(-5_000_000, 7);
+        assert_eq!(ladder.get(-5_000_000), Some(7));
+        assert_eq!(ladder.highest(), Some(7));
+    }
+
+    #[test]
+    fn test_max_slots() {
+        let mut ladder = LadderIndex::new(0, 99, 1).with_max_slots(1_000);
+        ladder.insert(50, 0);
+        assert!(ladder.accepts(1_049));
+        assert!(!ladder.accepts(1_050));
+        assert!(!ladder.accepts(-951));
+
+        let config = InstrumentConfig::default();
+        let mut book = OrderBook::<LadderIndex>::with_backend(config);
+        book.add_order(Order::new(1, Side::Buy, 10, 100, 0, 1)).unwrap();
+        assert_eq!(
+            book.add_order(Order::new(2, Side::Buy, 10, 100 + LadderIndex::MAX_SLOTS as Price, 0, 1)),
+            Err(crate::OrderBookError::PriceOutOfBand(100 + LadderIndex::MAX_SLOTS as Price))
+        );
+        assert!(!book.contains_order(2));
+        book.amend_order(1, 200_000, 10).unwrap();
+        assert_eq!(book.best_bid(), Some((200_000, 10)));
+    }
+
+    #[test]
+    fn test_out_of_window_order_rejected_before_matching() {
+        let config = InstrumentConfig::default();
+        let mut book = OrderBook::<LadderIndex>::with_backend(config);
+        book.add_order(Order::new(1, Side::Buy, 10, 1, 0, 1)).unwrap();
+        book.add_order(Order::new(2, Side::Sell, 5, 100, 0, 1)).unwrap();
+
+        // The remainder could not rest, so nothing trades
+        let price = 1 + 2 * LadderIndex::MAX_SLOTS as Price;
+        assert_eq!(
+            book.submit(Order::new(3, Side::Buy, 10, price, 0, 1)),
+            Err(crate::OrderBookError::PriceOutOfBand(price))
+        );
+        assert_eq!(book.best_ask(), Some((100, 5)));
+        assert_eq!(book.last_trade_price(), None);
+    }
+}
@@ -0,0 +1,264 @@
+//! Pluggable price-level indexes
+//!
+//! Each side of an `OrderBook` keeps its price levels in a `PriceLevelIndex`,
+//! which maps a price to the index of its `Limit` in the book's arena and
+//! answers ordered queries (best level, next level outwards). The book is
+//! generic over the index so the structure can be chosen per instrument:
+//!
+//! - `AvlIndex` (default): balanced tree plus price hash, works for any prices
+//! - `BTreeIndex`: the standard library's `BTreeMap`
+//! - `LadderIndex`: a dense array of slots, one per tick, with a bitset of
+//!   occupied levels; see the `ladder` module
+
+use crate::instrument::InstrumentConfig;
+use crate::types::Price;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Bound::{Excluded, Unbounded};
+
+/// Ordered map from price to limit index for one side of the book
+///
+/// Implementations hold each price at most once. The book only inserts
+/// prices that are not already present and that `accepts` allows.
+pub trait PriceLevelIndex: fmt::Debug {
+    /// Create an empty index suited to the instrument's rules
+    fn for_config(config: &InstrumentConfig) -> Self;
+
+    /// Whether a level at `price` can be stored
+    fn accepts(&self, _price: Price) -> bool {
+        true
+    }
+
+    /// Limit index of the level at `price`
+    fn get(&self, price: Price) -> Option<usize>;
+
+    /// Add a level
+    ///
+    /// # Panics
+    ///
+    /// May panic if `price` is already present or not accepted.
+    fn insert(&mut self, price: Price, limit_idx: usize);
+
+    /// Remove the level at `price`, returning its limit index
+    fn remove(&mut self, price: Price) -> Option<usize>;
+
+    /// Number of levels
+    fn len(&self) -> usize;
+
+    /// Check if there are no levels
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Limit index of the lowest-priced level
+    fn lowest(&self) -> Option<usize>;
+
+    /// Limit index of the highest-priced level
+    fn highest(&self) -> Option<usize>;
+
+    /// Limit index of the lowest level priced above `price`
+    fn next_higher(&self, price: Price) -> Option<usize>;
+
+    /// Limit index of the highest level priced below `price`
+    fn next_lower(&self, price: Price) -> Option<usize>;
+}
+
+/// Price-level index backed by a `BTreeMap`
+#[derive(Debug, Clone, Default)]
+pub struct BTreeIndex {
+    levels: BTreeMap<Price, usize>,
+}
+
+impl PriceLevelIndex for BTreeIndex {
+    fn for_config(_config: &InstrumentConfig) -> Self {
+        Self::default()
+    }
+
+    fn get(&self, price: Price) -> Option<usize> {
+        self.levels.get(&price).copied()
+    }
+
+    fn insert(&mut self, price: Price, limit_idx: usize) {
+        let previous = self.levels.insert(price, limit_idx);
+        debug_assert!(previous.is_none(), "level {} already indexed", price);
+    }
+
+    fn remove(&mut self, price: Price) -> Option<usize> {
+        self.levels.remove(&price)
+    }
+
+    fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    fn lowest(&self) -> Option<usize> {
+        self.levels.values().next().copied()
+    }
+
+    fn highest(&self) -> Option<usize> {
+        self.levels.values().next_back().copied()
+    }
+
+    fn next_higher(&self, price: Price) -> Option<usize> {
+        self.levels.range((Excluded(price), Unbounded)).next().map(|(_, &idx)| idx)
+    }
+
+    fn next_lower(&self, price: Price) -> Option<usize> {
+        self.levels.range(..price).next_back().map(|(_, &idx)| idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avl_tree::AvlIndex;
+    use crate::ladder::LadderIndex;
+    use crate::{Order, OrderBook, OrderType, Side};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Instrument every backend can hold: prices 1..=500 on a tick of 1
+    fn config() -> InstrumentConfig {
+        InstrumentConfig::default().with_price_band(1, 500)
+    }
+
+    /// Check every ordered query of `index` against a reference map
+    fn assert_matches<I: PriceLevelIndex>(index: &I, reference: &BTreeMap<Price, usize>) {
+        assert_eq!(index.len(), reference.len());
+        assert_eq!(index.is_empty(), reference.is_empty());
+        assert_eq!(index.lowest(), reference.values().next().copied());
+        assert_eq!(index.highest(), reference.values().next_back().copied());
+        for price in -2..=503 {
+            assert_eq!(index.get(price), reference.get(&price).copied(), "get {}", price);
+            assert_eq!(
+                index.next_higher(price),
+                reference.range((Excluded(price), Unbounded)).next().map(|(_, &i)| i),
+                "next_higher {}",
+                price
+            );
+            assert_eq!(
+                index.next_lower(price),
+                reference.range(..price).next_back().map(|(_, &i)| i),
+                "next_lower {}",
+                price
+            );
+        }
+    }
+
+    /// Random inserts and removals, compared against a `BTreeMap` model
+    fn index_conformance<I: PriceLevelIndex>() {
+        let mut index = I::for_config(&config());
+        let mut reference = BTreeMap::new();
+        let mut rng = StdRng::seed_from_u64(11);
+        assert_matches(&index, &reference);
+
+        for step in 0..3000usize {
+            let price = rng.gen_range(1..=500);
+            if let Some(idx) = reference.remove(&price) {
+                assert_eq!(index.remove(price), Some(idx));
+            } else {
+                assert!(index.accepts(price));
+                index.insert(price, step);
+                reference.insert(price, step);
+            }
+            assert_eq!(index.remove(price + 1000), None);
+            if step % 250 == 0 {
+                assert_matches(&index, &reference);
+            }
+        }
+        assert_matches(&index, &reference);
+
+        while let Some((&price, &idx)) = reference.iter().next() {
+            assert_eq!(index.remove(price), Some(idx));
+            reference.remove(&price);
+        }
+        assert_matches(&index, &reference);
+    }
+
+    /// Levels of both sides in book order, with their FIFO queues
+    fn book_state<I: PriceLevelIndex>(book: &OrderBook<I>) -> Vec<(Side, Price, Vec<u64>)> {
+        [(Side::Buy, book.bids()), (Side::Sell, book.asks())]
+            .into_iter()
+            .flat_map(|(side, levels)| {
+                levels.map(move |limit| {
+                    let queue = book.orders_at_level(side, limit.price).map(|o| o.id).collect();
+                    (side, limit.price, queue)
+                })
+            })
+            .collect()
+    }
+
+    /// Drive a book and the reference AVL book with the same random flow
+    fn book_conformance<I: PriceLevelIndex>() {
+        let mut book = OrderBook::<I>::with_backend(config());
+        let mut reference = OrderBook::<AvlIndex>::with_backend(config());
+        let mut rng = StdRng::seed_from_u64(23);
+        let mut live: Vec<u64> = Vec::new();
+
+        for id in 0..2000u64 {
+            match rng.gen_range(0..10) {
+                0..=2 if !live.is_empty() => {
+                    let victim = live.swap_remove(rng.gen_range(0..live.len()));
+                    assert_eq!(book.remove_order(victim), reference.remove_order(victim));
+                }
+                3 if !live.is_empty() => {
+                    let target = live[rng.gen_range(0..live.len())];
+                    let price = rng.gen_range(200..=300);
+                    let quantity = rng.gen_range(1..50);
+                    assert_eq!(
+                        book.amend_order(target, price, quantity),
+                        reference.amend_order(target, price, quantity)
+                    );
+                }
+                _ => {
+                    let side = if rng.gen_bool(0.5) { Side::Buy } else { Side::Sell };
+                    let price = rng.gen_range(200..=300);
+                    let order_type = if rng.gen_bool(0.1) { OrderType::ImmediateOrCancel } else { OrderType::Limit };
+                    let order = Order::new(id, side, rng.gen_range(1..50), price, id, 1).with_order_type(order_type);
+                    let report = book.submit(order.clone()).unwrap();
+                    assert_eq!(report, reference.submit(order).unwrap());
+                    live.retain(|&id| reference.contains_order(id));
+                    if reference.contains_order(id) {
+                        live.push(id);
+                    }
+                }
+            }
+
+            assert_eq!(book.best_bid(), reference.best_bid());
+            assert_eq!(book.best_ask(), reference.best_ask());
+            if id % 100 == 0 {
+                assert_eq!(book_state(&book), book_state(&reference));
+            }
+        }
+
+        assert_eq!(book.total_levels(), reference.total_levels());
+        assert_eq!(book.total_orders(), reference.total_orders());
+        assert_eq!(book_state(&book), book_state(&reference));
+    }
+
+    macro_rules! conformance_suite {
+        ($($name:ident: $index:ty,)*) => {
+            $(
+                mod $name {
+                    use super::*;
+
+                    #[test]
+                    fn index_matches_reference() {
+                        index_conformance::<$index>();
+                    }
+
+                    #[test]
+                    fn book_matches_avl_book() {
+                        book_conformance::<$index>();
+                    }
+                }
+            )*
+        };
+    }
+
+    conformance_suite! {
+        avl: AvlIndex,
+        btree: BTreeIndex,
+        ladder: LadderIndex,
+    }
+}
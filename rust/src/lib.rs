@@ -8,8 +8,21 @@
 //! ## Core Components
 //!
 //! - `Order`: Individual order with price, quantity, and metadata
-//! - `Limit`: Price level containing orders at the same price (AVL tree node)
-//! - `OrderBook`: Main order book managing buy and sell trees
+//! - `Limit`: Price level containing orders at the same price
+//! - `OrderBook`: Main order book managing buy and sell price levels
+//! - `level_index`: Pluggable price-level indexes (AVL tree, `BTreeMap`, dense ladder)
+//! - `ladder`: Dense tick ladder with a bitset of occupied levels that re-centers as prices drift
+//! - `instrument`: Tick size, lot size and price band rules attached to each book
+//! - `decimal`: Exact conversion between decimal strings and integer price/quantity units
+//! - `matching`: Price-time priority matching via `OrderBook::submit`, with self-trade prevention
+//! - `stops`: Stop and stop-limit orders parked until the last trade price reaches them
+//! - `auction`: Opening and closing call auctions uncrossed at a single equilibrium price
+//! - `events`: Incremental `BookEvent`s recorded for every book mutation
+//! - `market_data`: Sequenced L2 (price-level) and L3 (market-by-order) feeds built from those events
+//! - `snapshot`: `BookSnapshot` capture and restore for warm restarts
+//! - `journal`: Checksummed binary write-ahead journal of book mutations, with replay
+//! - `itch`: NASDAQ TotalView-ITCH 5.0 feed handler maintaining one book per stock
+//! - `manager`: `BookManager` routing orders to one book per instrument
 //!
 //! ## Performance Characteristics
 //!
@@ -24,13 +37,41 @@
 pub mod order;
 pub mod limit;
 pub mod orderbook;
+pub mod instrument;
+pub mod decimal;
+pub mod matching;
+pub mod stops;
+pub mod auction;
 pub mod avl_tree;
+pub mod level_index;
+pub mod ladder;
+pub mod events;
+pub mod iter;
+pub mod market_data;
+pub mod snapshot;
+pub mod journal;
+pub mod itch;
+pub mod manager;
 pub mod types;
 
 pub use order::Order;
 pub use limit::Limit;
 pub use orderbook::OrderBook;
-pub use types::{OrderId, Price, Quantity, Side, Timestamp, Trade};
+pub use avl_tree::AvlIndex;
+pub use level_index::{BTreeIndex, PriceLevelIndex};
+pub use ladder::LadderIndex;
+pub use instrument::InstrumentConfig;
+pub use decimal::{Decimal, DecimalScale};
+pub use matching::{ExecutionReport, PreventedTrade};
+pub use auction::{AuctionResult, IndicativeUncross};
+pub use events::{BookEvent, LevelDelta};
+pub use iter::{LevelOrders, Levels};
+pub use market_data::{L2Feed, L2Snapshot, L2Update, L3Feed, L3Snapshot, L3Update};
+pub use snapshot::BookSnapshot;
+pub use journal::{JournaledBook, ReplayLimit};
+pub use itch::{ItchHandler, ItchMessage};
+pub use manager::{BookManager, ManagerStats};
+pub use types::{AmendResult, InstrumentId, OrderId, OrderType, OwnerId, Price, Quantity, SelfTradePrevention, Side, Timestamp, Trade, TradingPhase};
 
 #[cfg(test)]
 mod tests;
@@ -49,14 +90,38 @@ pub enum OrderBookError {
     OrderAlreadyExists(OrderId),
     /// Order with given ID not found
     OrderNotFound(OrderId),
-    /// Invalid price (must be positive)
+    /// Invalid price (must be positive unless the instrument allows negative prices)
     InvalidPrice(Price),
     /// Invalid quantity (must be positive)
     InvalidQuantity(Quantity),
+    /// Price is not a multiple of the tick size
+    InvalidTick(Price),
+    /// Quantity is not a multiple of the lot size
+    InvalidLot(Quantity),
+    /// Quantity is outside the instrument's min/max limits
+    QuantityOutOfRange(Quantity),
+    /// Price is outside the instrument's price band
+    PriceOutOfBand(Price),
+    /// Decimal string cannot be represented exactly at the instrument's scale
+    InvalidDecimal(String),
+    /// Order type cannot be used for this operation
+    InvalidOrderType(OrderType),
+    /// Operation is not accepted in the book's current trading phase
+    NotAllowedInPhase(TradingPhase),
     /// Limit level not found
     LimitNotFound(Price),
+    /// Instrument with given ID is not registered
+    InstrumentNotFound(InstrumentId),
+    /// Instrument with given ID is already registered
+    InstrumentAlreadyExists(InstrumentId),
     /// Internal tree structure error
     TreeError(String),
+    /// Snapshot content is inconsistent
+    InvalidSnapshot(String),
+    /// Journal I/O failure or corrupt journal record
+    JournalError(String),
+    /// Malformed market data feed message
+    FeedError(String),
 }
 
 impl std::fmt::Display for OrderBookError {
@@ -66,8 +131,20 @@ impl std::fmt::Display for OrderBookError {
             OrderBookError::OrderNotFound(id) => write!(f, "Order {} not found", id),
             OrderBookError::InvalidPrice(price) => write!(f, "Invalid price: {}", price),
             OrderBookError::InvalidQuantity(qty) => write!(f, "Invalid quantity: {}", qty),
+            OrderBookError::InvalidTick(price) => write!(f, "Price {} is not a multiple of the tick size", price),
+            OrderBookError::InvalidLot(qty) => write!(f, "Quantity {} is not a multiple of the lot size", qty),
+            OrderBookError::QuantityOutOfRange(qty) => write!(f, "Quantity {} is outside the allowed range", qty),
+            OrderBookError::PriceOutOfBand(price) => write!(f, "Price {} is outside the price band", price),
+            OrderBookError::InvalidDecimal(s) => write!(f, "Invalid decimal: {:?}", s),
+            OrderBookError::InvalidOrderType(order_type) => write!(f, "Invalid order type: {}", order_type),
+            OrderBookError::NotAllowedInPhase(phase) => write!(f, "Operation not allowed in the {} phase", phase),
             OrderBookError::LimitNotFound(price) => write!(f, "Limit at price {} not found", price),
+            OrderBookError::InstrumentNotFound(id) => write!(f, "Instrument {} not found", id),
+            OrderBookError::InstrumentAlreadyExists(id) => write!(f, "Instrument {} already exists", id),
             OrderBookError::TreeError(msg) => write!(f, "Tree error: {}", msg),
+            OrderBookError::InvalidSnapshot(msg) => write!(f, "Invalid snapshot: {}", msg),
+            OrderBookError::JournalError(msg) => write!(f, "Journal error: {}", msg),
+            OrderBookError::FeedError(msg) => write!(f, "Feed error: {}", msg),
         }
     }
 }
@@ -1,6 +1,5 @@
 //! Limit (price level) implementation for the HFT orderbook
 
-use crate::avl_tree::AvlNode;
 use crate::types::{Price, Quantity, Side};
 use std::fmt;
 
@@ -10,26 +9,35 @@ use serde::{Deserialize, Serialize};
 /// Represents a price level in the orderbook
 /// 
 /// Each limit contains all orders at the same price level, organized as a doubly-linked list.
-/// Limits are organized in an AVL tree structure for efficient price-based operations.
+/// Limits are kept in price order by the book's `PriceLevelIndex` and linked to
+/// their neighbouring levels on the same side, so the next level is one hop away.
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
 pub struct Limit {
     /// The price for this limit level
     pub price: Price,
-    /// Total quantity at this price level
+    /// Total displayed quantity at this price level
     pub size: Quantity,
+    /// Quantity at this level that is not displayed: iceberg reserves and hidden orders
+    pub hidden_size: Quantity,
     /// Total volume (price * size) at this price level
-    pub total_volume: u128,
-    /// Number of orders at this price level
+    pub total_volume: i128,
+    /// Number of orders at this price level, hidden ones included
     pub order_count: usize,
+    /// Number of hidden orders at this price level
+    pub hidden_order_count: usize,
     /// Which side this limit belongs to (buy or sell tree)
     pub(crate) side: Side,
-    /// AVL tree node information
-    pub(crate) avl_node: AvlNode,
     /// Index of the first order in the doubly-linked list (None if empty)
     pub(crate) head_order_index: Option<usize>,
     /// Index of the last order in the doubly-linked list (None if empty)
     pub(crate) tail_order_index: Option<usize>,
+    /// Index of the first hidden order; hidden orders queue behind all displayed ones
+    pub(crate) first_hidden_order_index: Option<usize>,
+    /// Index of the adjacent limit at the next lower price on the same side
+    pub(crate) lower_limit_index: Option<usize>,
+    /// Index of the adjacent limit at the next higher price on the same side
+    pub(crate) higher_limit_index: Option<usize>,
 }
 
 impl Limit {
@@ -38,12 +46,16 @@ impl Limit {
         Self {
             price,
             size: 0,
+            hidden_size: 0,
             total_volume: 0,
             order_count: 0,
+            hidden_order_count: 0,
             side,
-            avl_node: AvlNode::new(),
             head_order_index: None,
             tail_order_index: None,
+            first_hidden_order_index: None,
+            lower_limit_index: None,
+            higher_limit_index: None,
         }
     }
 
@@ -61,7 +73,7 @@ impl Limit {
     /// This updates the statistics but doesn't manage the linked list structure
     pub fn add_order_stats(&mut self, quantity: Quantity) {
         self.size += quantity;
-        self.total_volume += self.price as u128 * quantity as u128;
+        self.total_volume += self.price as i128 * quantity as i128;
         self.order_count += 1;
     }
 
@@ -72,7 +84,7 @@ impl Limit {
         debug_assert!(self.order_count > 0, "Cannot remove order from empty limit");
         
         self.size -= quantity;
-        self.total_volume -= self.price as u128 * quantity as u128;
+        self.total_volume -= self.price as i128 * quantity as i128;
         self.order_count -= 1;
     }
 
@@ -81,46 +93,45 @@ impl Limit {
         if new_quantity > old_quantity {
             let diff = new_quantity - old_quantity;
             self.size += diff;
-            self.total_volume += self.price as u128 * diff as u128;
+            self.total_volume += self.price as i128 * diff as i128;
         } else if old_quantity > new_quantity {
             let diff = old_quantity - new_quantity;
             self.size -= diff;
-            self.total_volume -= self.price as u128 * diff as u128;
+            self.total_volume -= self.price as i128 * diff as i128;
         }
     }
 
-    /// Get the total value at this limit level
-    pub fn total_value(&self) -> u128 {
-        self.total_volume
-    }
-
-    /// Check if this limit is a leaf node in the AVL tree
-    pub fn is_leaf(&self) -> bool {
-        self.avl_node.is_leaf()
+    /// Update statistics when an order's hidden quantity changes
+    pub fn update_hidden_stats(&mut self, old_hidden: Quantity, new_hidden: Quantity) {
+        debug_assert!(self.hidden_size >= old_hidden, "Cannot remove more hidden quantity than available");
+        self.hidden_size = self.hidden_size - old_hidden + new_hidden;
     }
 
-    /// Check if this limit has only a left child
-    pub fn has_only_left_child(&self) -> bool {
-        self.avl_node.has_only_left_child()
+    /// Number of orders shown at this level
+    pub fn displayed_order_count(&self) -> usize {
+        self.order_count - self.hidden_order_count
     }
 
-    /// Check if this limit has only a right child
-    pub fn has_only_right_child(&self) -> bool {
-        self.avl_node.has_only_right_child()
+    /// Displayed and hidden quantity together
+    pub fn total_size(&self) -> Quantity {
+        self.size + self.hidden_size
     }
 
-    /// Check if this limit has both children
-    pub fn has_both_children(&self) -> bool {
-        self.avl_node.has_both_children()
+    /// Get the total value at this limit level
+    pub fn total_value(&self) -> i128 {
+        self.total_volume
     }
 
     /// Reset the limit to empty state (used when all orders are removed)
     pub fn reset(&mut self) {
         self.size = 0;
+        self.hidden_size = 0;
         self.total_volume = 0;
         self.order_count = 0;
+        self.hidden_order_count = 0;
         self.head_order_index = None;
         self.tail_order_index = None;
+        self.first_hidden_order_index = None;
     }
 }
 
@@ -160,7 +171,6 @@ mod tests {
         assert_eq!(limit.total_volume, 0);
         assert_eq!(limit.order_count, 0);
         assert!(limit.is_empty());
-        assert!(limit.is_leaf());
     }
 
     #[test]
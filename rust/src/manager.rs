@@ -0,0 +1,430 @@
+//! Multi-instrument book management
+//!
+//! `BookManager` owns one `OrderBook` per instrument and routes orders, cancels
+//! and queries to the right book. A global order ID index maps every resting
+//! order and parked stop order to its instrument, so cancels and amendments only need the order ID.
+//! Order IDs are therefore unique across all instruments, not just per book.
+
+use crate::auction::AuctionResult;
+use crate::instrument::InstrumentConfig;
+use crate::matching::ExecutionReport;
+use crate::order::Order;
+use crate::orderbook::OrderBook;
+use crate::types::{AmendResult, InstrumentId, OrderId, Price, Quantity, Timestamp, TradingPhase};
+use crate::{OrderBookError, Result};
+use std::collections::HashMap;
+
+/// Aggregate statistics across all books of a `BookManager`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ManagerStats {
+    /// Number of instruments
+    pub instruments: usize,
+    /// Resting orders across all books
+    pub orders: usize,
+    /// Price levels across all books
+    pub levels: usize,
+    /// Instruments with at least one resting order
+    pub active_instruments: usize,
+}
+
+/// Collection of order books keyed by instrument
+#[derive(Debug, Default)]
+pub struct BookManager {
+    books: HashMap<InstrumentId, OrderBook>,
+    /// Instrument of every resting order and parked stop order
+    order_instruments: HashMap<OrderId, InstrumentId>,
+}
+
+impl BookManager {
+    /// Create an empty manager
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an instrument with an empty book
+    pub fn add_instrument(&mut self, instrument: InstrumentId) -> Result<()> {
+        self.insert_book(instrument, OrderBook::new())
+    }
+
+    /// Register an instrument with an empty book enforcing `config`
+    pub fn add_instrument_with_config(&mut self, instrument: InstrumentId, config: InstrumentConfig) -> Result<()> {
+        self.insert_book(instrument, OrderBook::with_config(config))
+    }
+
+    /// Register an instrument with an existing book, e.g. one restored from a snapshot
+    pub fn insert_book(&mut self, instrument: InstrumentId, book: OrderBook) -> Result<()> {
+        if self.books.contains_key(&instrument) {
+            return Err(OrderBookError::InstrumentAlreadyExists(instrument));
+        }
+        let order_ids: Vec<_> = Self::order_ids(&book).collect();
+        if let Some(&order_id) = order_ids.iter().find(|id| self.order_instruments.contains_key(id)) {
+            return Err(OrderBookError::OrderAlreadyExists(order_id));
+        }
+
+        for order_id in order_ids {
+            self.order_instruments.insert(order_id, instrument);
+        }
+        self.books.insert(instrument, book);
+        Ok(())
+    }
+
+    /// Unregister an instrument and return its book
+    pub fn remove_instrument(&mut self, instrument: InstrumentId) -> Result<OrderBook> {
+        let book = self.books
+            .remove(&instrument)
+            .ok_or(OrderBookError::InstrumentNotFound(instrument))?;
+        for order_id in Self::order_ids(&book) {
+            self.order_instruments.remove(&order_id);
+        }
+        Ok(book)
+    }
+
+    /// Check whether an instrument is registered
+    pub fn contains_instrument(&self, instrument: InstrumentId) -> bool {
+        self.books.contains_key(&instrument)
+    }
+
+    /// Iterate over registered instruments, in no particular order
+    pub fn instruments(&self) -> impl Iterator<Item = InstrumentId> + '_ {
+        self.books.keys().copied()
+    }
+
+    /// Get an instrument's book for queries
+    pub fn book(&self, instrument: InstrumentId) -> Option<&OrderBook> {
+        self.books.get(&instrument)
+    }
+
+    /// Instrument an order rests on
+    pub fn instrument_of(&self, order_id: OrderId) -> Option<InstrumentId> {
+        self.order_instruments.get(&order_id).copied()
+    }
+
+    /// Look up a resting order by ID alone
+    pub fn get_order(&self, order_id: OrderId) -> Option<&Order> {
+        let instrument = self.instrument_of(order_id)?;
+        self.books.get(&instrument)?.get_order(order_id)
+    }
+
+    /// Best bid of an instrument
+    pub fn best_bid(&self, instrument: InstrumentId) -> Option<(Price, Quantity)> {
+        self.book(instrument)?.best_bid()
+    }
+
+    /// Best ask of an instrument
+    pub fn best_ask(&self, instrument: InstrumentId) -> Option<(Price, Quantity)> {
+        self.book(instrument)?.best_ask()
+    }
+
+    /// Set the current timestamp on every book
+    pub fn set_time(&mut self, timestamp: Timestamp) {
+        for book in self.books.values_mut() {
+            book.set_time(timestamp);
+        }
+    }
+
+    /// Move an instrument's book to another trading phase
+    pub fn set_phase(&mut self, instrument: InstrumentId, phase: TradingPhase) -> Result<()> {
+        self.book_mut(instrument)?.set_phase(phase)
+    }
+
+    /// Start a call auction on an instrument's book
+    pub fn start_call_auction(&mut self, instrument: InstrumentId, reference_price: Option<Price>) -> Result<()> {
+        self.book_mut(instrument)?.start_call_auction(reference_price)
+    }
+
+    /// Uncross an instrument's call auction
+    pub fn uncross(&mut self, instrument: InstrumentId) -> Result<AuctionResult> {
+        let book = self.books
+            .get_mut(&instrument)
+            .ok_or(OrderBookError::InstrumentNotFound(instrument))?;
+        let result = book.uncross()?;
+
+        for trade in &result.trades {
+            for order_id in [trade.aggressor_order_id, trade.passive_order_id] {
+                if !book.contains_order(order_id) {
+                    self.order_instruments.remove(&order_id);
+                }
+            }
+        }
+        for report in &result.triggered {
+            Self::track_report(&mut self.order_instruments, book, instrument, report);
+        }
+        Ok(result)
+    }
+
+    /// Add an order to an instrument's book without matching
+    pub fn add_order(&mut self, instrument: InstrumentId, order: Order) -> Result<()> {
+        let order_id = order.id;
+        if self.order_instruments.contains_key(&order_id) {
+            return Err(OrderBookError::OrderAlreadyExists(order_id));
+        }
+        self.book_mut(instrument)?.add_order(order)?;
+        self.order_instruments.insert(order_id, instrument);
+        Ok(())
+    }
+
+    /// Submit an order for matching on an instrument's book
+    pub fn submit(&mut self, instrument: InstrumentId, order: Order) -> Result<ExecutionReport> {
+        if self.order_instruments.contains_key(&order.id) {
+            return Err(OrderBookError::OrderAlreadyExists(order.id));
+        }
+
+        let book = self.books
+            .get_mut(&instrument)
+            .ok_or(OrderBookError::InstrumentNotFound(instrument))?;
+        let report = book.submit(order)?;
+        Self::track_report(&mut self.order_instruments, book, instrument, &report);
+        Ok(report)
+    }
+
+    /// Remove an order from whichever book it rests on
+    pub fn remove_order(&mut self, order_id: OrderId) -> Result<Order> {
+        let instrument = self.routed(order_id)?;
+        let order = self.book_mut(instrument)?.remove_order(order_id)?;
+        self.order_instruments.remove(&order_id);
+        Ok(order)
+    }
+
+    /// Update an order's quantity, keeping its queue position
+    pub fn update_order(&mut self, order_id: OrderId, new_quantity: Quantity) -> Result<()> {
+        let instrument = self.routed(order_id)?;
+        self.book_mut(instrument)?.update_order(order_id, new_quantity)
+    }
+
+    /// Amend an order's price and/or quantity with exchange priority rules
+    pub fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Price,
+        new_quantity: Quantity,
+    ) -> Result<AmendResult> {
+        let instrument = self.routed(order_id)?;
+        self.book_mut(instrument)?.amend_order(order_id, new_price, new_quantity)
+    }
+
+    /// Total resting and parked stop orders across all books
+    pub fn total_orders(&self) -> usize {
+        self.order_instruments.len()
+    }
+
+    /// Aggregate statistics across all books
+    pub fn stats(&self) -> ManagerStats {
+        self.books.values().fold(
+            ManagerStats {
+                instruments: self.books.len(),
+                ..ManagerStats::default()
+            },
+            |mut stats, book| {
+                let orders = book.total_orders();
+                stats.orders += orders;
+                stats.levels += book.total_levels();
+                if orders > 0 {
+                    stats.active_instruments += 1;
+                }
+                stats
+            },
+        )
+    }
+
+    fn book_mut(&mut self, instrument: InstrumentId) -> Result<&mut OrderBook> {
+        self.books
+            .get_mut(&instrument)
+            .ok_or(OrderBookError::InstrumentNotFound(instrument))
+    }
+
+    fn routed(&self, order_id: OrderId) -> Result<InstrumentId> {
+        self.instrument_of(order_id)
+            .ok_or(OrderBookError::OrderNotFound(order_id))
+    }
+
+    /// IDs of every order resting or parked in `book`
+    fn order_ids(book: &OrderBook) -> impl Iterator<Item = OrderId> + '_ {
+        book.orders.iter().flatten().chain(book.stop_orders()).map(|order| order.id)
+    }
+
+    /// Update routing after a submission to `book`, then after each stop it triggered
+    ///
+    /// The reports are visited in execution order, so an order that rested
+    /// and was then filled by a later stop is dropped again.
+    fn track_report(
+        order_instruments: &mut HashMap<OrderId, InstrumentId>,
+        book: &OrderBook,
+        instrument: InstrumentId,
+        report: &ExecutionReport,
+    ) {
+        if report.rested || report.parked {
+            order_instruments.insert(report.order_id, instrument);
+        } else {
+            order_instruments.remove(&report.order_id);
+        }
+        let touched = report.trades.iter().map(|trade| trade.passive_order_id)
+            .chain(report.prevented.iter().map(|prevented| prevented.resting_order_id));
+        for order_id in touched {
+            if !book.contains_order(order_id) {
+                order_instruments.remove(&order_id);
+            }
+        }
+        for triggered in &report.triggered {
+            Self::track_report(order_instruments, book, instrument, triggered);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{SelfTradePrevention, Side};
+
+    fn manager() -> BookManager {
+        let mut manager = BookManager::new();
+        manager.add_instrument(1).unwrap();
+        manager.add_instrument(2).unwrap();
+        manager.set_time(1000);
+        manager
+    }
+
+    #[test]
+    fn test_routing() {
+        let mut manager = manager();
+        manager.add_order(1, Order::new(10, Side::Buy, 100, 5000, 1000, 1)).unwrap();
+        manager.add_order(2, Order::new(20, Side::Sell, 50, 9000, 1000, 1)).unwrap();
+
+        assert_eq!(manager.best_bid(1), Some((5000, 100)));
+        assert_eq!(manager.best_bid(2), None);
+        assert_eq!(manager.best_ask(2), Some((9000, 50)));
+        assert_eq!(manager.instrument_of(20), Some(2));
+        assert_eq!(manager.get_order(20).unwrap().quantity, 50);
+
+        manager.update_order(10, 60).unwrap();
+        manager.amend_order(20, 8900, 50).unwrap();
+        assert_eq!(manager.best_bid(1), Some((5000, 60)));
+        assert_eq!(manager.best_ask(2), Some((8900, 50)));
+
+        assert_eq!(manager.remove_order(10).unwrap().id, 10);
+        assert_eq!(manager.instrument_of(10), None);
+        assert_eq!(manager.remove_order(10), Err(OrderBookError::OrderNotFound(10)));
+    }
+
+    #[test]
+    fn test_order_ids_are_global() {
+        let mut manager = manager();
+        manager.add_order(1, Order::new(10, Side::Buy, 100, 5000, 1000, 1)).unwrap();
+        assert_eq!(
+            manager.add_order(2, Order::new(10, Side::Buy, 100, 5000, 1000, 1)),
+            Err(OrderBookError::OrderAlreadyExists(10))
+        );
+        assert_eq!(
+            manager.add_order(3, Order::new(11, Side::Buy, 100, 5000, 1000, 1)),
+            Err(OrderBookError::InstrumentNotFound(3))
+        );
+        assert_eq!(manager.add_instrument(1), Err(OrderBookError::InstrumentAlreadyExists(1)));
+        assert_eq!(manager.total_orders(), 1);
+    }
+
+    #[test]
+    fn test_submit_keeps_index_in_sync() {
+        let mut manager = manager();
+        manager.add_order(1, Order::new(10, Side::Sell, 30, 5000, 1000, 1)).unwrap();
+        manager.add_order(1, Order::new(11, Side::Sell, 30, 5010, 1000, 1)).unwrap();
+
+        let report = manager.submit(1, Order::new(12, Side::Buy, 50, 5010, 1000, 1)).unwrap();
+        assert_eq!(report.filled_quantity, 50);
+        assert_eq!(manager.instrument_of(10), None);
+        assert_eq!(manager.instrument_of(11), Some(1));
+        assert_eq!(manager.instrument_of(12), None);
+
+        let report = manager.submit(1, Order::new(13, Side::Buy, 20, 5010, 1000, 1)).unwrap();
+        assert!(report.rested);
+        assert_eq!(manager.instrument_of(11), None);
+        assert_eq!(manager.instrument_of(13), Some(1));
+        assert_eq!(manager.total_orders(), 1);
+    }
+
+    #[test]
+    fn test_self_trade_cancellations_leave_the_index() {
+        let mut manager = manager();
+        manager.add_order(1, Order::new(10, Side::Sell, 30, 5000, 1000, 1).with_owner(4)).unwrap();
+
+        let order = Order::new(11, Side::Buy, 10, 5000, 1000, 1)
+            .with_owner(4)
+            .with_self_trade_prevention(SelfTradePrevention::CancelOldest);
+        let report = manager.submit(1, order).unwrap();
+        assert_eq!(report.prevented.len(), 1);
+        assert_eq!(manager.instrument_of(10), None);
+        assert_eq!(manager.instrument_of(11), Some(1));
+        assert_eq!(manager.remove_order(10), Err(OrderBookError::OrderNotFound(10)));
+    }
+
+    #[test]
+    fn test_stops_are_routed() {
+        let mut manager = manager();
+        manager.add_order(1, Order::new(10, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+        manager.add_order(1, Order::new(11, Side::Sell, 10, 5010, 1000, 1)).unwrap();
+        manager.submit(1, Order::market(12, Side::Buy, 10, 1000, 1).with_stop(5000)).unwrap();
+        manager.submit(1, Order::market(13, Side::Buy, 10, 1000, 1).with_stop(6000)).unwrap();
+        assert_eq!(manager.instrument_of(12), Some(1));
+        assert_eq!(manager.total_orders(), 4);
+
+        // 14 fills 10, firing 12, which fills 11
+        let report = manager.submit(1, Order::new(14, Side::Buy, 10, 5000, 1000, 1)).unwrap();
+        assert_eq!(report.triggered[0].trades[0].passive_order_id, 11);
+        assert_eq!(manager.instrument_of(11), None);
+        assert_eq!(manager.instrument_of(12), None);
+
+        assert_eq!(manager.remove_order(13).unwrap().stop_price, Some(6000));
+        assert_eq!(manager.total_orders(), 0);
+    }
+
+    #[test]
+    fn test_auction_per_instrument() {
+        let mut manager = manager();
+        manager.start_call_auction(1, None).unwrap();
+        manager.set_phase(2, TradingPhase::Halted).unwrap();
+        assert_eq!(
+            manager.add_order(2, Order::new(20, Side::Buy, 10, 700, 1000, 1)),
+            Err(OrderBookError::NotAllowedInPhase(TradingPhase::Halted))
+        );
+        assert_eq!(manager.instrument_of(20), None);
+
+        manager.submit(1, Order::new(10, Side::Buy, 10, 5001, 1000, 1)).unwrap();
+        manager.submit(1, Order::new(11, Side::Sell, 4, 5000, 1000, 1)).unwrap();
+        let result = manager.uncross(1).unwrap();
+        assert_eq!(result.volume, 4);
+        assert_eq!(manager.instrument_of(10), Some(1));
+        assert_eq!(manager.instrument_of(11), None);
+        assert_eq!(manager.book(1).unwrap().phase(), TradingPhase::Continuous);
+    }
+
+    #[test]
+    fn test_instrument_lifecycle_and_stats() {
+        let mut manager = manager();
+        manager.add_order(1, Order::new(10, Side::Buy, 100, 5000, 1000, 1)).unwrap();
+        manager.add_order(1, Order::new(11, Side::Sell, 100, 5100, 1000, 1)).unwrap();
+        manager.add_order(2, Order::new(20, Side::Buy, 100, 700, 1000, 1)).unwrap();
+        manager.add_instrument_with_config(3, InstrumentConfig::new(10, 1)).unwrap();
+        assert_eq!(
+            manager.add_order(3, Order::new(30, Side::Buy, 1, 705, 1000, 1)),
+            Err(OrderBookError::InvalidTick(705))
+        );
+        assert_eq!(manager.instrument_of(30), None);
+
+        assert_eq!(manager.stats(), ManagerStats {
+            instruments: 3,
+            orders: 3,
+            levels: 3,
+            active_instruments: 2,
+        });
+
+        let book = manager.remove_instrument(1).unwrap();
+        assert_eq!(book.total_orders(), 2);
+        assert_eq!(manager.instrument_of(10), None);
+        assert_eq!(manager.total_orders(), 1);
+
+        let mut restored = OrderBook::restore(&book.snapshot()).unwrap();
+        restored.add_order(Order::new(20, Side::Buy, 1, 4000, 1000, 1)).unwrap();
+        assert_eq!(manager.insert_book(4, restored), Err(OrderBookError::OrderAlreadyExists(20)));
+        manager.insert_book(4, book).unwrap();
+        assert_eq!(manager.instrument_of(11), Some(4));
+        assert_eq!(manager.stats().orders, 3);
+    }
+}
@@ -0,0 +1,593 @@
+//! Market data generated from book events
+//!
+//! `L2Feed` turns the `BookEvent`s drained from an `OrderBook` into sequenced
+//! price-level updates, carrying the level's displayed size and order count
+//! straight from its `Limit`. It can also produce a full snapshot on demand,
+//! stamped with the feed's current sequence number so consumers can line it
+//! up with the incremental stream.
+//!
+//! `L3Feed` is the market-by-order counterpart: every resting order is published
+//! individually, with snapshots walking each level's FIFO list so that orders
+//! appear in price-time priority.
+//!
+//! Hidden orders are left out of both feeds.
+
+use crate::events::BookEvent;
+use crate::iter::Levels;
+use crate::level_index::PriceLevelIndex;
+use crate::limit::Limit;
+use crate::order::Order;
+use crate::orderbook::OrderBook;
+use crate::types::{ExchangeId, OrderId, Price, Quantity, Side, Timestamp};
+use std::collections::HashSet;
+
+#[cfg(feature = "serde_support")]
+use serde::{Deserialize, Serialize};
+
+/// Sequence number attached to market data messages
+pub type SequenceNumber = u64;
+
+/// What happened to a price level
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum L2Action {
+    /// A level appeared at this price
+    New,
+    /// An existing level's size or order count changed
+    Change,
+    /// The level emptied and should be removed
+    Delete,
+}
+
+/// Incremental update for a single price level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct L2Update {
+    /// Feed sequence number, starting at 1
+    pub sequence: SequenceNumber,
+    /// Side of the level
+    pub side: Side,
+    /// Price of the level
+    pub price: Price,
+    /// New aggregate size (0 on delete)
+    pub size: Quantity,
+    /// New number of orders (0 on delete)
+    pub order_count: usize,
+    /// Kind of update
+    pub action: L2Action,
+    /// Book time when the update was generated
+    pub timestamp: Timestamp,
+}
+
+/// Aggregated state of a single price level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct L2Level {
+    /// Price of the level
+    pub price: Price,
+    /// Aggregate size
+    pub size: Quantity,
+    /// Number of orders
+    pub order_count: usize,
+}
+
+/// Full price-level view of the book
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct L2Snapshot {
+    /// Sequence number of the last update published before this snapshot
+    pub sequence: SequenceNumber,
+    /// Book time when the snapshot was taken
+    pub timestamp: Timestamp,
+    /// Bid levels, highest price first
+    pub bids: Vec<L2Level>,
+    /// Ask levels, lowest price first
+    pub asks: Vec<L2Level>,
+}
+
+/// Generator of sequenced level-2 updates and snapshots
+#[derive(Debug, Clone, Default)]
+pub struct L2Feed {
+    /// Sequence number of the last published update
+    sequence: SequenceNumber,
+}
+
+impl L2Feed {
+    /// Create a new feed with no updates published
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sequence number of the last published update
+    pub fn sequence(&self) -> SequenceNumber {
+        self.sequence
+    }
+
+    /// Produce the level updates for a batch of events
+    ///
+    /// `events` should be everything drained from `book` since the previous
+    /// call, and `book` must already reflect them. Each touched level yields at
+    /// most one update, in the order the levels were first touched. Levels
+    /// created and removed within the same batch produce nothing.
+    pub fn process<I: PriceLevelIndex>(&mut self, book: &OrderBook<I>, events: &[BookEvent]) -> Vec<L2Update> {
+        // (side, price, whether the level was displayed before this batch)
+        let mut touched: Vec<(Side, Price, bool)> = Vec::new();
+        let mut seen: HashSet<(Side, Price)> = HashSet::new();
+
+        let mut touch = |side: Side, price: Price, created: bool| {
+            if seen.insert((side, price)) {
+                touched.push((side, price, !created));
+            }
+        };
+
+        for event in events {
+            match *event {
+                BookEvent::LevelCreated { side, price, .. } => touch(side, price, true),
+                BookEvent::LevelRemoved { side, price, .. } => touch(side, price, false),
+                BookEvent::OrderAdded { level, .. }
+                | BookEvent::OrderCancelled { level, .. }
+                | BookEvent::OrderFilled { level, .. } => touch(level.side, level.price, false),
+                BookEvent::OrderModified { old_level, new_level, .. } => {
+                    touch(old_level.side, old_level.price, false);
+                    touch(new_level.side, new_level.price, false);
+                }
+                BookEvent::BboChanged { .. }
+                | BookEvent::PhaseChanged { .. }
+                | BookEvent::StopTriggered { .. }
+                | BookEvent::StopRejected { .. } => {}
+            }
+        }
+
+        let timestamp = book.current_time();
+        let mut updates = Vec::with_capacity(touched.len());
+        for (side, price, existed) in touched {
+            let size = book.volume_at_price_for_side(side, price);
+            let order_count = book.orders_at_price_for_side(side, price);
+
+            let (action, size, order_count) = match (size, order_count) {
+                (Some(size), Some(count)) if existed => (L2Action::Change, size, count),
+                (Some(size), Some(count)) => (L2Action::New, size, count),
+                _ if existed => (L2Action::Delete, 0, 0),
+                _ => continue,
+            };
+
+            self.sequence += 1;
+            updates.push(L2Update {
+                sequence: self.sequence,
+                side,
+                price,
+                size,
+                order_count,
+                action,
+                timestamp,
+            });
+        }
+
+        updates
+    }
+
+    /// Drain the book's pending events and produce the resulting level updates
+    ///
+    /// Requires event recording to be enabled on `book`.
+    pub fn publish<I: PriceLevelIndex>(&mut self, book: &mut OrderBook<I>) -> Vec<L2Update> {
+        let events = book.drain_events();
+        self.process(book, &events)
+    }
+
+    /// Take a full snapshot of the book, optionally limited to `depth` levels per side
+    pub fn snapshot<I: PriceLevelIndex>(&self, book: &OrderBook<I>, depth: Option<usize>) -> L2Snapshot {
+        let depth = depth.unwrap_or(usize::MAX);
+        let level = |limit: &Limit| L2Level {
+            price: limit.price,
+            size: limit.size,
+            order_count: limit.displayed_order_count(),
+        };
+
+        L2Snapshot {
+            sequence: self.sequence,
+            timestamp: book.current_time(),
+            bids: book.bids().take(depth).map(level).collect(),
+            asks: book.asks().take(depth).map(level).collect(),
+        }
+    }
+}
+
+/// A single resting order as published on the L3 feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct L3Order {
+    /// Order ID
+    pub order_id: OrderId,
+    /// Side of the order
+    pub side: Side,
+    /// Limit price
+    pub price: Price,
+    /// Open quantity
+    pub quantity: Quantity,
+    /// Time the order gained its current queue position
+    pub entry_time: Timestamp,
+    /// Exchange identifier
+    pub exchange_id: ExchangeId,
+}
+
+impl From<&Order> for L3Order {
+    fn from(order: &Order) -> Self {
+        Self {
+            order_id: order.id,
+            side: order.side,
+            price: order.price,
+            quantity: order.quantity,
+            entry_time: order.entry_time,
+            exchange_id: order.exchange_id,
+        }
+    }
+}
+
+/// Market-by-order message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum L3Message {
+    /// A new order joined the back of its level
+    Add(L3Order),
+    /// An order's price and/or quantity changed
+    ///
+    /// If `priority_lost` is set the order moved to the back of the level at `price`.
+    Modify {
+        order_id: OrderId,
+        side: Side,
+        price: Price,
+        quantity: Quantity,
+        priority_lost: bool,
+    },
+    /// An order was removed without trading
+    Delete {
+        order_id: OrderId,
+        side: Side,
+        price: Price,
+    },
+    /// A resting order traded; it leaves the book once `remaining_quantity` is 0
+    Execute {
+        order_id: OrderId,
+        side: Side,
+        price: Price,
+        quantity: Quantity,
+        remaining_quantity: Quantity,
+        aggressor_order_id: OrderId,
+    },
+}
+
+/// Sequenced market-by-order message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct L3Update {
+    /// Feed sequence number, starting at 1
+    pub sequence: SequenceNumber,
+    /// Book time of the underlying event
+    pub timestamp: Timestamp,
+    /// The message itself
+    pub message: L3Message,
+}
+
+/// Every resting order in the book
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct L3Snapshot {
+    /// Sequence number of the last update published before this snapshot
+    pub sequence: SequenceNumber,
+    /// Book time when the snapshot was taken
+    pub timestamp: Timestamp,
+    /// Bid orders, best price first and FIFO within each price
+    pub bids: Vec<L3Order>,
+    /// Ask orders, best price first and FIFO within each price
+    pub asks: Vec<L3Order>,
+}
+
+/// Generator of sequenced level-3 (market-by-order) messages and snapshots
+#[derive(Debug, Clone, Default)]
+pub struct L3Feed {
+    /// Sequence number of the last published update
+    sequence: SequenceNumber,
+}
+
+impl L3Feed {
+    /// Create a new feed with no updates published
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sequence number of the last published update
+    pub fn sequence(&self) -> SequenceNumber {
+        self.sequence
+    }
+
+    /// Translate a batch of book events into market-by-order messages
+    ///
+    /// Unlike the L2 feed nothing is coalesced: every order event produces
+    /// exactly one message, in event order. Level and BBO events are ignored.
+    pub fn process(&mut self, events: &[BookEvent]) -> Vec<L3Update> {
+        let mut updates = Vec::with_capacity(events.len());
+
+        for event in events {
+            let message = match *event {
+                BookEvent::OrderAdded { order_id, quantity, entry_time, exchange_id, level, .. } => {
+                    L3Message::Add(L3Order {
+                        order_id,
+                        side: level.side,
+                        price: level.price,
+                        quantity,
+                        entry_time,
+                        exchange_id,
+                    })
+                }
+                BookEvent::OrderModified { order_id, new_quantity, priority_lost, new_level, .. } => {
+                    L3Message::Modify {
+                        order_id,
+                        side: new_level.side,
+                        price: new_level.price,
+                        quantity: new_quantity,
+                        priority_lost,
+                    }
+                }
+                BookEvent::OrderCancelled { order_id, level, .. } => L3Message::Delete {
+                    order_id,
+                    side: level.side,
+                    price: level.price,
+                },
+                BookEvent::OrderFilled {
+                    order_id,
+                    aggressor_order_id,
+                    quantity,
+                    remaining_quantity,
+                    level,
+                    ..
+                } => L3Message::Execute {
+                    order_id,
+                    side: level.side,
+                    price: level.price,
+                    quantity,
+                    remaining_quantity,
+                    aggressor_order_id,
+                },
+                BookEvent::LevelCreated { .. }
+                | BookEvent::LevelRemoved { .. }
+                | BookEvent::BboChanged { .. }
+                | BookEvent::PhaseChanged { .. }
+                | BookEvent::StopTriggered { .. }
+                | BookEvent::StopRejected { .. } => continue,
+            };
+
+            self.sequence += 1;
+            updates.push(L3Update {
+                sequence: self.sequence,
+                timestamp: event.timestamp(),
+                message,
+            });
+        }
+
+        updates
+    }
+
+    /// Drain the book's pending events and translate them into messages
+    ///
+    /// Requires event recording to be enabled on `book`.
+    pub fn publish<I: PriceLevelIndex>(&mut self, book: &mut OrderBook<I>) -> Vec<L3Update> {
+        let events = book.drain_events();
+        self.process(&events)
+    }
+
+    /// List every resting order in price-time priority
+    pub fn snapshot<I: PriceLevelIndex>(&self, book: &OrderBook<I>) -> L3Snapshot {
+        let side_orders = |levels: Levels<'_, I>, side: Side| -> Vec<L3Order> {
+            levels
+                .flat_map(|limit| book.orders_at_level(side, limit.price).take_while(|order| !order.hidden))
+                .map(L3Order::from)
+                .collect()
+        };
+
+        L3Snapshot {
+            sequence: self.sequence,
+            timestamp: book.current_time(),
+            bids: side_orders(book.bids(), Side::Buy),
+            asks: side_orders(book.asks(), Side::Sell),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Order;
+
+    fn book_with_events() -> OrderBook {
+        let mut book = OrderBook::new();
+        book.set_events_enabled(true);
+        book.set_time(1000);
+        book
+    }
+
+    #[test]
+    fn test_new_change_delete() {
+        let mut book = book_with_events();
+        let mut feed = L2Feed::new();
+
+        book.add_order(Order::new(1, Side::Buy, 10, 5000, 1000, 1)).unwrap();
+        let updates = feed.publish(&mut book);
+        assert_eq!(updates, vec![L2Update {
+            sequence: 1,
+            side: Side::Buy,
+            price: 5000,
+            size: 10,
+            order_count: 1,
+            action: L2Action::New,
+            timestamp: 1000,
+        }]);
+
+        book.add_order(Order::new(2, Side::Buy, 5, 5000, 1001, 1)).unwrap();
+        let updates = feed.publish(&mut book);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].sequence, 2);
+        assert_eq!(updates[0].action, L2Action::Change);
+        assert_eq!((updates[0].size, updates[0].order_count), (15, 2));
+
+        book.remove_order(1).unwrap();
+        book.remove_order(2).unwrap();
+        let updates = feed.publish(&mut book);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].action, L2Action::Delete);
+        assert_eq!((updates[0].size, updates[0].order_count), (0, 0));
+        assert_eq!(feed.sequence(), 3);
+    }
+
+    #[test]
+    fn test_sweep_touches_each_level_once() {
+        let mut book = book_with_events();
+        let mut feed = L2Feed::new();
+
+        book.add_order(Order::new(1, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+        book.add_order(Order::new(2, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+        book.add_order(Order::new(3, Side::Sell, 10, 5010, 1000, 1)).unwrap();
+        feed.publish(&mut book);
+
+        book.submit(Order::new(4, Side::Buy, 25, 5020, 1001, 1)).unwrap();
+        let updates = feed.publish(&mut book);
+        let summary: Vec<_> = updates.iter()
+            .map(|u| (u.side, u.price, u.size, u.action))
+            .collect();
+        assert_eq!(summary, vec![
+            (Side::Sell, 5000, 0, L2Action::Delete),
+            (Side::Sell, 5010, 5, L2Action::Change),
+        ]);
+    }
+
+    #[test]
+    fn test_transient_level_is_skipped() {
+        let mut book = book_with_events();
+        let mut feed = L2Feed::new();
+
+        book.add_order(Order::new(1, Side::Buy, 10, 5000, 1000, 1)).unwrap();
+        book.remove_order(1).unwrap();
+        assert!(feed.publish(&mut book).is_empty());
+        assert_eq!(feed.sequence(), 0);
+    }
+
+    #[test]
+    fn test_price_amend_deletes_vacated_level() {
+        let mut book = book_with_events();
+        let mut feed = L2Feed::new();
+        book.add_order(Order::new(1, Side::Buy, 10, 5000, 1000, 1)).unwrap();
+        feed.publish(&mut book);
+
+        book.amend_order(1, 4990, 10).unwrap();
+        let updates: Vec<_> = feed.publish(&mut book).iter().map(|u| (u.price, u.action)).collect();
+        assert_eq!(updates, vec![(5000, L2Action::Delete), (4990, L2Action::New)]);
+    }
+
+    #[test]
+    fn test_hidden_orders_are_not_published() {
+        let mut book = book_with_events();
+        let mut l2 = L2Feed::new();
+        let mut l3 = L3Feed::new();
+
+        book.add_order(Order::new(1, Side::Buy, 10, 5000, 1000, 1).with_hidden(true)).unwrap();
+        let events = book.drain_events();
+        assert!(l2.process(&book, &events).is_empty());
+        assert!(l3.process(&events).is_empty());
+
+        // The first displayed order makes the level appear
+        book.add_order(Order::new(2, Side::Buy, 5, 5000, 1001, 1)).unwrap();
+        let updates = l2.publish(&mut book);
+        assert_eq!(updates[0].action, L2Action::New);
+        assert_eq!((updates[0].size, updates[0].order_count), (5, 1));
+        assert_eq!(l2.snapshot(&book, None).bids[0].order_count, 1);
+        assert_eq!(l3.snapshot(&book).bids.len(), 1);
+
+        // and the last one makes it disappear, though order 1 still rests
+        book.remove_order(2).unwrap();
+        assert_eq!(l2.publish(&mut book)[0].action, L2Action::Delete);
+        assert!(l2.snapshot(&book, None).bids.is_empty());
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let mut book = book_with_events();
+        let mut feed = L2Feed::new();
+
+        book.add_order(Order::new(1, Side::Buy, 10, 4990, 1000, 1)).unwrap();
+        book.add_order(Order::new(2, Side::Buy, 20, 4980, 1000, 1)).unwrap();
+        book.add_order(Order::new(3, Side::Buy, 30, 4990, 1000, 1)).unwrap();
+        book.add_order(Order::new(4, Side::Sell, 40, 5010, 1000, 1)).unwrap();
+        feed.publish(&mut book);
+
+        let snapshot = feed.snapshot(&book, Some(1));
+        assert_eq!(snapshot.sequence, 3);
+        assert_eq!(snapshot.bids, vec![L2Level { price: 4990, size: 40, order_count: 2 }]);
+        assert_eq!(snapshot.asks, vec![L2Level { price: 5010, size: 40, order_count: 1 }]);
+
+        let snapshot = feed.snapshot(&book, None);
+        assert_eq!(snapshot.bids.len(), 2);
+    }
+
+    #[test]
+    fn test_l3_snapshot_price_time_order() {
+        let mut book = OrderBook::new();
+        book.set_time(1000);
+        book.add_order(Order::new(1, Side::Buy, 10, 4990, 1000, 7)).unwrap();
+        book.add_order(Order::new(2, Side::Buy, 20, 5000, 1001, 7)).unwrap();
+        book.add_order(Order::new(3, Side::Buy, 30, 4990, 1002, 8)).unwrap();
+        book.add_order(Order::new(4, Side::Sell, 40, 5020, 1003, 8)).unwrap();
+        book.add_order(Order::new(5, Side::Sell, 50, 5010, 1004, 8)).unwrap();
+        book.add_order(Order::new(6, Side::Sell, 60, 5010, 1005, 8)).unwrap();
+
+        let snapshot = L3Feed::new().snapshot(&book);
+        let bids: Vec<_> = snapshot.bids.iter().map(|o| o.order_id).collect();
+        let asks: Vec<_> = snapshot.asks.iter().map(|o| o.order_id).collect();
+        assert_eq!(bids, vec![2, 1, 3]);
+        assert_eq!(asks, vec![5, 6, 4]);
+        assert_eq!(snapshot.bids[2], L3Order {
+            order_id: 3,
+            side: Side::Buy,
+            price: 4990,
+            quantity: 30,
+            entry_time: 1002,
+            exchange_id: 8,
+        });
+    }
+
+    #[test]
+    fn test_l3_deltas() {
+        let mut book = OrderBook::new();
+        book.set_events_enabled(true);
+        book.set_time(1000);
+        let mut feed = L3Feed::new();
+
+        book.add_order(Order::new(1, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+        book.add_order(Order::new(2, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+        book.set_time(1001);
+        book.amend_order(2, 5010, 10).unwrap();
+        book.submit(Order::new(3, Side::Buy, 4, 5000, 1001, 1)).unwrap();
+        book.remove_order(2).unwrap();
+
+        let messages: Vec<_> = feed.publish(&mut book).into_iter().map(|u| (u.sequence, u.message)).collect();
+        assert_eq!(messages, vec![
+            (1, L3Message::Add(L3Order {
+                order_id: 1, side: Side::Sell, price: 5000, quantity: 10, entry_time: 1000, exchange_id: 1,
+            })),
+            (2, L3Message::Add(L3Order {
+                order_id: 2, side: Side::Sell, price: 5000, quantity: 10, entry_time: 1000, exchange_id: 1,
+            })),
+            (3, L3Message::Modify {
+                order_id: 2, side: Side::Sell, price: 5010, quantity: 10, priority_lost: true,
+            }),
+            (4, L3Message::Execute {
+                order_id: 1,
+                side: Side::Sell,
+                price: 5000,
+                quantity: 4,
+                remaining_quantity: 6,
+                aggressor_order_id: 3,
+            }),
+            (5, L3Message::Delete { order_id: 2, side: Side::Sell, price: 5010 }),
+        ]);
+        assert_eq!(feed.sequence(), 5);
+    }
+}
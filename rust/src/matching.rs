@@ -0,0 +1,421 @@
+//! Price-time priority matching layered on top of the passive `OrderBook`
+//!
+//! `OrderBook::add_order` only stores orders. `OrderBook::submit` first walks the
+//! opposite side of the book from the best price outwards, filling resting orders
+//! head-to-tail within each `Limit`, and only rests whatever quantity is left.
+//!
+//! The order's `OrderType` decides what happens to that remainder: limit orders
+//! rest, market and IOC orders are cancelled, and fill-or-kill orders are only
+//! executed when the whole quantity is available up front.
+//!
+//! Orders carrying the same `owner_id` never trade with each other through
+//! `submit` (call auction uncrosses are exempt, see `auction`). When the
+//! incoming order reaches a resting order of its own owner, its
+//! `SelfTradePrevention` mode decides which side is cancelled or reduced, and
+//! the outcome is listed in `ExecutionReport::prevented`.
+
+use crate::events::{BookEvent, LevelDelta};
+use crate::iter::LevelOrders;
+use crate::level_index::PriceLevelIndex;
+use crate::order::Order;
+use crate::orderbook::OrderBook;
+use crate::types::{OrderId, OrderType, Price, Quantity, SelfTradePrevention, Side, Trade};
+use crate::{OrderBookError, Result};
+
+#[cfg(feature = "serde_support")]
+use serde::{Deserialize, Serialize};
+
+/// Outcome of submitting an order to the matching engine
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct ExecutionReport {
+    /// ID of the submitted order
+    pub order_id: OrderId,
+    /// Side of the submitted order
+    pub side: Side,
+    /// Trades generated against resting orders, in execution order
+    pub trades: Vec<Trade>,
+    /// Total quantity executed
+    pub filled_quantity: Quantity,
+    /// Quantity left after matching and self-trade prevention
+    pub remaining_quantity: Quantity,
+    /// Whether the remaining quantity was added to the book
+    pub rested: bool,
+    /// Whether the order was parked as a stop waiting for its trigger
+    pub parked: bool,
+    /// Matches stopped by self-trade prevention, in the order they were met
+    pub prevented: Vec<PreventedTrade>,
+    /// Stop orders triggered by this order's trades, in firing order
+    pub triggered: Vec<ExecutionReport>,
+    /// Why a triggered stop order could not be submitted; nothing was executed
+    pub rejection: Option<OrderBookError>,
+}
+
+/// A match against a resting order of the same owner that was not executed
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct PreventedTrade {
+    /// ID of the resting order
+    pub resting_order_id: OrderId,
+    /// Price of the resting order
+    pub price: Price,
+    /// Mode of the incoming order that was applied
+    pub action: SelfTradePrevention,
+    /// Quantity cancelled from the incoming order
+    pub incoming_cancelled: Quantity,
+    /// Quantity cancelled from the resting order
+    pub resting_cancelled: Quantity,
+}
+
+impl ExecutionReport {
+    fn new(order_id: OrderId, side: Side) -> Self {
+        Self {
+            order_id,
+            side,
+            trades: Vec::new(),
+            filled_quantity: 0,
+            remaining_quantity: 0,
+            rested: false,
+            parked: false,
+            prevented: Vec::new(),
+            triggered: Vec::new(),
+            rejection: None,
+        }
+    }
+
+    /// Report for a triggered stop order that was dropped instead of submitted
+    pub(crate) fn rejected(order_id: OrderId, side: Side, quantity: Quantity, error: OrderBookError) -> Self {
+        let mut report = Self::new(order_id, side);
+        report.remaining_quantity = quantity;
+        report.rejection = Some(error);
+        report
+    }
+
+    /// Check if the order was completely filled
+    pub fn is_filled(&self) -> bool {
+        self.remaining_quantity == 0 && self.self_trade_cancelled() == 0
+    }
+
+    /// Quantity of the submitted order cancelled by self-trade prevention
+    pub fn self_trade_cancelled(&self) -> Quantity {
+        self.prevented.iter().map(|p| p.incoming_cancelled).sum()
+    }
+
+    /// Quantity that was neither filled nor rested or parked
+    pub fn cancelled_quantity(&self) -> Quantity {
+        let remainder = if self.rested || self.parked { 0 } else { self.remaining_quantity };
+        remainder + self.self_trade_cancelled()
+    }
+}
+
+impl<I: PriceLevelIndex> OrderBook<I> {
+    /// Submit an order for matching
+    ///
+    /// The order is matched against the opposite side in price-time priority.
+    /// Each trade executes at the resting order's price. Unfilled quantity of a
+    /// limit order is then added to the book; for every other order type it is
+    /// cancelled and reported in `ExecutionReport::cancelled_quantity`.
+    ///
+    /// In the `PreOpen` and `AuctionCall` phases limit orders rest without
+    /// matching and other order types are rejected; see the `auction` module.
+    ///
+    /// A stop order is parked until the last trade price reaches its stop
+    /// price, and any stops triggered by this order's trades are submitted
+    /// before returning; see the `stops` module.
+    pub fn submit(&mut self, mut order: Order) -> Result<ExecutionReport> {
+        self.check_phase(self.phase.accepts_orders())?;
+        if order.order_type.has_limit_price() {
+            self.config.validate_price(order.price)?;
+        }
+        // Checked before matching so a remainder that could not rest does
+        // not leave the order half executed
+        if order.order_type.can_rest() {
+            self.check_price_fits(order.side, order.price)?;
+        }
+        if let Some(stop_price) = order.stop_price {
+            self.config.validate_price(stop_price)?;
+        }
+        self.validate_order_quantity(&order)?;
+        if self.contains_order(order.id) || self.stops.contains(order.id) {
+            return Err(OrderBookError::OrderAlreadyExists(order.id));
+        }
+
+        order.event_time = self.current_time;
+
+        if order.is_stop() {
+            let triggered = self.phase.matches_orders()
+                && self.last_trade_price.is_some_and(|last| order.stop_triggered_by(last));
+            if !triggered {
+                let mut report = ExecutionReport::new(order.id, order.side);
+                report.remaining_quantity = order.total_quantity();
+                report.parked = true;
+                self.stops.insert(order);
+                return Ok(report);
+            }
+            order = self.trigger_stop(order);
+        }
+
+        let mut report = self.execute(order)?;
+        report.triggered = self.fire_stops();
+        Ok(report)
+    }
+
+    /// Match an order and rest or cancel the remainder, without firing stops
+    pub(crate) fn execute(&mut self, mut order: Order) -> Result<ExecutionReport> {
+        let mut report = ExecutionReport::new(order.id, order.side);
+        if !self.phase.matches_orders() {
+            if !order.order_type.can_rest() {
+                return Err(OrderBookError::InvalidOrderType(order.order_type));
+            }
+            report.remaining_quantity = order.total_quantity();
+            report.rested = true;
+            let bbo_before = self.bbo_before();
+            self.add_order_to_book(order)?;
+            self.emit_bbo_change(bbo_before);
+            return Ok(report);
+        }
+
+        // An iceberg trades its whole quantity on arrival and only hides
+        // what is left once it rests
+        order.reveal_reserve();
+        let bbo_before = self.bbo_before();
+        if order.order_type == OrderType::FillOrKill
+            && self.crossing_liquidity(&order) < order.quantity
+        {
+            report.remaining_quantity = order.quantity;
+            return Ok(report);
+        }
+
+        self.match_order(&mut order, &mut report);
+
+        report.remaining_quantity = order.quantity;
+        if !order.is_filled() && order.order_type.can_rest() {
+            if order.is_iceberg() {
+                order.split_peak();
+            }
+            self.add_order_to_book(order)?;
+            report.rested = true;
+        }
+        self.emit_bbo_change(bbo_before);
+
+        Ok(report)
+    }
+
+    /// Match an incoming order against the opposite side of the book
+    fn match_order(&mut self, order: &mut Order, report: &mut ExecutionReport) {
+        while !order.is_filled() {
+            let best_index = match order.side {
+                Side::Buy => self.best_ask_index,
+                Side::Sell => self.best_bid_index,
+            };
+            let Some(limit_idx) = best_index else {
+                break;
+            };
+
+            let level_price = self.limits[limit_idx].as_ref().unwrap().price;
+            if !Self::crosses(order, level_price) {
+                break;
+            }
+
+            self.match_at_limit(order, limit_idx, level_price, report);
+        }
+    }
+
+    /// Fill an incoming order against the FIFO queue of a single limit
+    ///
+    /// Returns when either the incoming order is filled or the limit is exhausted
+    /// (in which case it has already been removed from the book).
+    fn match_at_limit(
+        &mut self,
+        order: &mut Order,
+        limit_idx: usize,
+        level_price: Price,
+        report: &mut ExecutionReport,
+    ) {
+        let now = self.current_time;
+
+        while !order.is_filled() {
+            let Some(passive_idx) = self.limits[limit_idx].as_ref().unwrap().head_order_index else {
+                break;
+            };
+
+            if order.same_owner(self.orders[passive_idx].as_ref().unwrap()) {
+                let limit_emptied = self.limits[limit_idx].as_ref().unwrap().order_count == 1;
+                let resting_removed = self.prevent_self_trade(order, passive_idx, level_price, report);
+                if resting_removed && limit_emptied {
+                    break;
+                }
+                continue;
+            }
+
+            let passive_quantity = self.orders[passive_idx].as_ref().unwrap().quantity;
+            let filled = order.quantity.min(passive_quantity);
+            let passive_id = self.orders[passive_idx].as_ref().unwrap().id;
+
+            order.fill(filled, now);
+            self.last_trade_price = Some(level_price);
+            report.filled_quantity += filled;
+            report.trades.push(Trade::new(
+                order.id,
+                passive_id,
+                level_price,
+                filled,
+                now,
+                order.side,
+            ));
+
+            if self.fill_resting_order(passive_idx, limit_idx, filled, order.id) {
+                break;
+            }
+        }
+    }
+
+    /// Execute part or all of a resting order against `counterparty_id`
+    ///
+    /// Updates the level size, records `OrderFilled` (unless the order is
+    /// hidden) and removes the order once it is filled; an iceberg order with
+    /// reserve left is replenished instead. Returns true if the removal emptied
+    /// and removed its limit.
+    pub(crate) fn fill_resting_order(
+        &mut self,
+        order_idx: usize,
+        limit_idx: usize,
+        quantity: Quantity,
+        counterparty_id: OrderId,
+    ) -> bool {
+        let now = self.current_time;
+        let order = self.orders[order_idx].as_mut().unwrap();
+        let old_quantity = order.quantity;
+        let (old_displayed, old_undisplayed) = (order.displayed_quantity(), order.undisplayed_quantity());
+        let filled = order.fill(quantity, now);
+        let order_id = order.id;
+        let hidden = order.hidden;
+        let slice_filled = order.quantity == 0;
+        let has_reserve = order.hidden_quantity > 0;
+
+        let limit = self.limits[limit_idx].as_mut().unwrap();
+        let size_before = limit.size;
+        limit.update_order_stats(old_displayed, order.displayed_quantity());
+        limit.update_hidden_stats(old_undisplayed, order.undisplayed_quantity());
+        let level = LevelDelta::new(limit.side, limit.price, size_before, limit.size);
+        if !hidden {
+            self.emit(BookEvent::OrderFilled {
+                order_id,
+                aggressor_order_id: counterparty_id,
+                quantity: filled,
+                remaining_quantity: old_quantity - filled,
+                level,
+                timestamp: now,
+            });
+        }
+
+        if !slice_filled {
+            return false;
+        }
+        if has_reserve {
+            self.replenish_iceberg(order_idx, limit_idx);
+            return false;
+        }
+        let limit_emptied = self.limits[limit_idx].as_ref().unwrap().order_count == 1;
+        self.remove_order_from_limit(order_idx, limit_idx)
+            .expect("resting order should be linked to its limit");
+        limit_emptied
+    }
+
+    /// Apply the incoming order's self-trade prevention mode to a resting order
+    /// of the same owner
+    ///
+    /// Returns true if the resting order was removed from the book.
+    fn prevent_self_trade(
+        &mut self,
+        order: &mut Order,
+        passive_idx: usize,
+        level_price: Price,
+        report: &mut ExecutionReport,
+    ) -> bool {
+        let passive = self.orders[passive_idx].as_ref().unwrap();
+        let resting_order_id = passive.id;
+        let resting_quantity = passive.total_quantity();
+        let action = order.self_trade_prevention;
+
+        let (incoming_cancelled, resting_cancelled) = match action {
+            SelfTradePrevention::CancelNewest => (order.quantity, 0),
+            SelfTradePrevention::CancelOldest => (0, resting_quantity),
+            SelfTradePrevention::CancelBoth => (order.quantity, resting_quantity),
+            SelfTradePrevention::DecrementAndCancel => {
+                let decrement = order.quantity.min(resting_quantity);
+                (decrement, decrement)
+            }
+        };
+
+        order.quantity -= incoming_cancelled;
+        let resting_removed = resting_cancelled == resting_quantity;
+        if resting_removed {
+            self.cancel_resting_order(passive_idx);
+        } else if resting_cancelled > 0 {
+            self.resize_resting_order(passive_idx, resting_quantity - resting_cancelled)
+                .expect("decremented quantity is non-zero");
+        }
+
+        report.prevented.push(PreventedTrade {
+            resting_order_id,
+            price: level_price,
+            action,
+            incoming_cancelled,
+            resting_cancelled,
+        });
+        resting_removed
+    }
+
+    /// Total resting quantity an incoming order could execute against
+    ///
+    /// Walks the opposite side from the best level and stops as soon as the
+    /// order's full quantity is covered, so the book is never touched. For an
+    /// order with an owner, resting orders of the same owner are skipped under
+    /// `CancelOldest`; any other mode stops matching there, so the walk ends.
+    fn crossing_liquidity(&self, order: &Order) -> Quantity {
+        let mut available: Quantity = 0;
+        let mut level = match order.side {
+            Side::Buy => self.best_ask_index,
+            Side::Sell => self.best_bid_index,
+        };
+
+        while let Some(limit_idx) = level {
+            let limit = self.limits[limit_idx].as_ref().unwrap();
+            if !Self::crosses(order, limit.price) {
+                break;
+            }
+            if order.owner_id.is_none() {
+                available += limit.total_size();
+            } else {
+                for resting in LevelOrders::new(&self.orders, limit.head_order_index) {
+                    if !order.same_owner(resting) {
+                        available += resting.total_quantity();
+                    } else if order.self_trade_prevention != SelfTradePrevention::CancelOldest {
+                        return available;
+                    }
+                    if available >= order.quantity {
+                        return available;
+                    }
+                }
+            }
+            if available >= order.quantity {
+                break;
+            }
+            level = self.next_worse_level(limit_idx);
+        }
+
+        available
+    }
+
+    /// Check whether an incoming order crosses a resting level
+    fn crosses(order: &Order, level_price: Price) -> bool {
+        if !order.order_type.has_limit_price() {
+            return true;
+        }
+        match order.side {
+            Side::Buy => order.price >= level_price,
+            Side::Sell => order.price <= level_price,
+        }
+    }
+}
@@ -1,6 +1,8 @@
 //! Order implementation for the HFT orderbook
 
-use crate::types::{OrderId, Price, Quantity, Side, Timestamp, ExchangeId};
+use crate::instrument::InstrumentConfig;
+use crate::types::{ExchangeId, OrderId, OrderType, OwnerId, Price, Quantity, SelfTradePrevention, Side, Timestamp};
+use crate::Result;
 use std::fmt;
 
 #[cfg(feature = "serde_support")]
@@ -35,28 +37,47 @@ pub struct Order {
     pub id: OrderId,
     /// Buy or Sell
     pub side: Side,
-    /// Order quantity (shares)
+    /// Order quantity (shares); for an iceberg order, the displayed part
     pub quantity: Quantity,
-    /// Limit price
+    /// Limit price (ignored for market orders)
     pub price: Price,
+    /// Execution instructions applied on submission
+    pub order_type: OrderType,
     /// Time when order was created
     pub entry_time: Timestamp,
     /// Time when order was last updated
     pub event_time: Timestamp,
     /// Exchange identifier
     pub exchange_id: ExchangeId,
+    /// Participant owning the order; orders of the same owner never trade with each other
+    pub owner_id: Option<OwnerId>,
+    /// Action taken when this order would trade against its owner's resting order
+    pub self_trade_prevention: SelfTradePrevention,
+    /// Reserve of an iceberg order, not shown in the book
+    pub hidden_quantity: Quantity,
+    /// Displayed slice size of an iceberg order (0 for a fully displayed order)
+    pub peak_size: Quantity,
+    /// Largest random deviation of each replenished slice from `peak_size`
+    pub peak_variance: Quantity,
+    /// Rests without being shown, behind the displayed orders at its price
+    pub hidden: bool,
+    /// Last trade price at which a stop order is triggered (None if not a stop)
+    pub stop_price: Option<Price>,
     /// Index of next order in the doubly-linked list (None if tail)
     /// 
     /// This is an index into `OrderBook.orders` vector, not a raw pointer.
     /// This allows safe circular references without violating Rust's borrow rules.
+    #[cfg_attr(feature = "serde_support", serde(skip))]
     pub(crate) next_order_index: Option<usize>,
     /// Index of previous order in the doubly-linked list (None if head)
     /// 
     /// This is an index into `OrderBook.orders` vector, not a raw pointer.
+    #[cfg_attr(feature = "serde_support", serde(skip))]
     pub(crate) prev_order_index: Option<usize>,
     /// Index of the parent limit level
     /// 
     /// This is an index into `OrderBook.limits` vector, not a raw pointer.
+    #[cfg_attr(feature = "serde_support", serde(skip))]
     pub(crate) parent_limit_index: Option<usize>,
 }
 
@@ -75,15 +96,171 @@ impl Order {
             side,
             quantity,
             price,
+            order_type: OrderType::Limit,
             entry_time,
             event_time: entry_time,
             exchange_id,
+            owner_id: None,
+            self_trade_prevention: SelfTradePrevention::default(),
+            hidden_quantity: 0,
+            peak_size: 0,
+            peak_variance: 0,
+            hidden: false,
+            stop_price: None,
             next_order_index: None,
             prev_order_index: None,
             parent_limit_index: None,
         }
     }
 
+    /// Create a new order from decimal quantity and price strings
+    ///
+    /// The strings are converted exactly using the instrument's scales, so
+    /// `"50.25"` becomes 5025 at a price scale of 2.
+    pub fn from_decimal(
+        id: OrderId,
+        side: Side,
+        quantity: &str,
+        price: &str,
+        entry_time: Timestamp,
+        exchange_id: ExchangeId,
+        config: &InstrumentConfig,
+    ) -> Result<Self> {
+        Ok(Self::new(
+            id,
+            side,
+            config.parse_quantity(quantity)?,
+            config.parse_price(price)?,
+            entry_time,
+            exchange_id,
+        ))
+    }
+
+    /// Create a new market order
+    pub fn market(
+        id: OrderId,
+        side: Side,
+        quantity: Quantity,
+        entry_time: Timestamp,
+        exchange_id: ExchangeId,
+    ) -> Self {
+        Self::new(id, side, quantity, 0, entry_time, exchange_id)
+            .with_order_type(OrderType::Market)
+    }
+
+    /// Set the order type
+    pub fn with_order_type(mut self, order_type: OrderType) -> Self {
+        self.order_type = order_type;
+        self
+    }
+
+    /// Set the owning participant, enabling self-trade prevention
+    pub fn with_owner(mut self, owner_id: OwnerId) -> Self {
+        self.owner_id = Some(owner_id);
+        self
+    }
+
+    /// Set the self-trade prevention mode applied when this order is submitted
+    pub fn with_self_trade_prevention(mut self, mode: SelfTradePrevention) -> Self {
+        self.self_trade_prevention = mode;
+        self
+    }
+
+    /// Make this an iceberg order displaying at most `peak` at a time
+    ///
+    /// The quantity given so far becomes the order's total: `peak` of it is
+    /// displayed and the rest is held in `hidden_quantity`. Each time the
+    /// displayed slice is filled, the book refills it from the reserve.
+    pub fn with_peak(mut self, peak: Quantity) -> Self {
+        self.peak_size = peak;
+        self.split_peak();
+        self
+    }
+
+    /// Randomize each replenished slice of an iceberg order by up to
+    /// `variance` either side of its peak size
+    pub fn with_peak_variance(mut self, variance: Quantity) -> Self {
+        self.peak_variance = variance;
+        self
+    }
+
+    /// Make this a hidden order
+    ///
+    /// A hidden order rests at its price level without showing in the book's
+    /// prices, volumes or feeds, and only matches once every displayed order
+    /// at that price has been filled.
+    pub fn with_hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Make this a stop order triggered at `stop_price`
+    ///
+    /// The order is parked outside the book until a trade prints at or above
+    /// the stop price for a buy, or at or below it for a sell. It is then
+    /// submitted as its order type: a market order gives a stop order, a
+    /// limit order a stop-limit order.
+    pub fn with_stop(mut self, stop_price: Price) -> Self {
+        self.stop_price = Some(stop_price);
+        self
+    }
+
+    /// Check if this is a stop order waiting for its trigger
+    pub fn is_stop(&self) -> bool {
+        self.stop_price.is_some()
+    }
+
+    /// Check if a trade at `last_price` triggers this stop order
+    pub fn stop_triggered_by(&self, last_price: Price) -> bool {
+        match (self.stop_price, self.side) {
+            (Some(stop), Side::Buy) => last_price >= stop,
+            (Some(stop), Side::Sell) => last_price <= stop,
+            (None, _) => false,
+        }
+    }
+
+    /// Check if this is an iceberg order
+    pub fn is_iceberg(&self) -> bool {
+        self.peak_size > 0
+    }
+
+    /// Displayed and hidden quantity together
+    pub fn total_quantity(&self) -> Quantity {
+        self.quantity + self.hidden_quantity
+    }
+
+    /// Quantity shown in the book: none for a hidden order, the peak for an iceberg
+    pub fn displayed_quantity(&self) -> Quantity {
+        if self.hidden {
+            0
+        } else {
+            self.quantity
+        }
+    }
+
+    /// Quantity resting in the book without being shown
+    pub fn undisplayed_quantity(&self) -> Quantity {
+        self.total_quantity() - self.displayed_quantity()
+    }
+
+    /// Move the whole reserve into the displayed quantity
+    pub(crate) fn reveal_reserve(&mut self) {
+        self.quantity += self.hidden_quantity;
+        self.hidden_quantity = 0;
+    }
+
+    /// Display one peak of the total quantity and hide the rest
+    pub(crate) fn split_peak(&mut self) {
+        let total = self.total_quantity();
+        self.quantity = total.min(self.peak_size);
+        self.hidden_quantity = total - self.quantity;
+    }
+
+    /// Check whether this order and `other` belong to the same owner
+    pub fn same_owner(&self, other: &Order) -> bool {
+        self.owner_id.is_some() && self.owner_id == other.owner_id
+    }
+
     /// Check if this is a buy order
     pub fn is_buy(&self) -> bool {
         self.side.is_buy()
@@ -94,14 +271,14 @@ impl Order {
         self.side.is_sell()
     }
 
-    /// Check if the order is completely filled
+    /// Check if the order is completely filled, reserve included
     pub fn is_filled(&self) -> bool {
-        self.quantity == 0
+        self.total_quantity() == 0
     }
 
     /// Calculate the total value of the order (price * quantity)
-    pub fn value(&self) -> u128 {
-        self.price as u128 * self.quantity as u128
+    pub fn value(&self) -> i128 {
+        self.price as i128 * self.quantity as i128
     }
 
     /// Fill the order by the specified quantity
@@ -119,12 +296,20 @@ impl Order {
     }
 
     /// Update the order quantity (for order modifications)
+    ///
+    /// For an iceberg order this is the new total: the displayed part only
+    /// shrinks if the total drops below it, and the reserve takes the rest.
     pub fn update_quantity(&mut self, new_quantity: Quantity, event_time: Timestamp) -> bool {
         if new_quantity == 0 {
             return false;
         }
 
-        self.quantity = new_quantity;
+        if self.is_iceberg() {
+            self.quantity = self.quantity.min(new_quantity);
+            self.hidden_quantity = new_quantity - self.quantity;
+        } else {
+            self.quantity = new_quantity;
+        }
         self.event_time = event_time;
         true
     }
@@ -140,6 +325,33 @@ impl fmt::Display for Order {
     }
 }
 
+/// Display adapter showing an order's price and quantity in decimal notation
+#[derive(Debug, Clone, Copy)]
+pub struct OrderDisplay<'a> {
+    order: &'a Order,
+    config: &'a InstrumentConfig,
+}
+
+impl Order {
+    /// Display the order with the instrument's decimal scales applied
+    pub fn display_with<'a>(&'a self, config: &'a InstrumentConfig) -> OrderDisplay<'a> {
+        OrderDisplay { order: self, config }
+    }
+}
+
+impl fmt::Display for OrderDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Order[{}]: {} {} @ {}",
+            self.order.id,
+            self.order.side,
+            self.config.quantity_decimal(self.order.quantity),
+            self.config.price_decimal(self.order.price)
+        )
+    }
+}
+
 impl PartialOrd for Order {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
@@ -166,9 +378,22 @@ mod tests {
         assert_eq!(order.side, Side::Buy);
         assert_eq!(order.quantity, 100);
         assert_eq!(order.price, 5000);
+        assert_eq!(order.order_type, OrderType::Limit);
         assert!(!order.is_filled());
     }
 
+    #[test]
+    fn test_market_order_creation() {
+        let order = Order::market(1, Side::Sell, 100, 1000, 1);
+        assert_eq!(order.order_type, OrderType::Market);
+        assert_eq!(order.price, 0);
+
+        let ioc = Order::new(2, Side::Buy, 100, 5000, 1000, 1)
+            .with_order_type(OrderType::ImmediateOrCancel);
+        assert_eq!(ioc.order_type, OrderType::ImmediateOrCancel);
+        assert_eq!(ioc.price, 5000);
+    }
+
     #[test]
     fn test_order_fill() {
         let mut order = Order::new(1, Side::Buy, 100, 5000, 1000, 1);
@@ -196,4 +421,23 @@ mod tests {
         assert_eq!(order.quantity, 0);
         assert!(order.is_filled());
     }
+
+    #[test]
+    fn test_iceberg_split() {
+        let order = Order::new(1, Side::Buy, 100, 5000, 1000, 1).with_peak(30);
+        assert!(order.is_iceberg());
+        assert_eq!((order.quantity, order.hidden_quantity, order.total_quantity()), (30, 70, 100));
+
+        let small = Order::new(2, Side::Buy, 20, 5000, 1000, 1).with_peak(30);
+        assert_eq!((small.quantity, small.hidden_quantity), (20, 0));
+
+        // A new total only eats into the displayed part once the reserve is gone
+        let mut order = order;
+        assert!(order.update_quantity(50, 1001));
+        assert_eq!((order.quantity, order.hidden_quantity), (30, 20));
+        assert!(order.update_quantity(10, 1002));
+        assert_eq!((order.quantity, order.hidden_quantity), (10, 0));
+        assert!(order.update_quantity(80, 1003));
+        assert_eq!((order.quantity, order.hidden_quantity), (10, 70));
+    }
 }
@@ -4,13 +4,21 @@
 //! - Pure order book data structure without matching logic
 //! - Basic operations: add, remove, update orders
 //! - Query operations: best prices, volume at levels, etc.
-//! - External matching engine handles order matching
-
-use crate::avl_tree::{AvlNode, AvlTree};
+//! - Matching is layered on top in the `matching` module
+
+use crate::avl_tree::AvlIndex;
+use crate::decimal::Decimal;
+use crate::events::{Bbo, BookEvent, LevelDelta};
+use crate::instrument::InstrumentConfig;
+use crate::iter::{LevelOrders, Levels};
+use crate::level_index::PriceLevelIndex;
 use crate::limit::Limit;
 use crate::order::Order;
-use crate::types::{OrderId, Price, Quantity, Side, Timestamp};
+use crate::stops::StopBook;
+use crate::types::{AmendResult, OrderId, Price, Quantity, Side, Timestamp, TradingPhase};
 use crate::{OrderBookError, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use std::collections::HashMap;
 
 /// Pure limit order book data structure
@@ -19,71 +27,150 @@ use std::collections::HashMap;
 /// - O(1) operations for add, cancel, and update orders
 /// - O(log M) for the first order at a new price level
 /// - O(1) queries for best bid/ask and volume at price levels
+///
+/// Price levels on each side are kept in order by a `PriceLevelIndex`; the
+/// AVL tree is the default, see the `level_index` module for the others.
 #[derive(Debug)]
-pub struct OrderBook {
+pub struct OrderBook<I: PriceLevelIndex = AvlIndex> {
     /// All orders indexed by order ID
-    orders: Vec<Option<Order>>,
+    pub(crate) orders: Vec<Option<Order>>,
     /// Free indices in the orders vector
     free_order_indices: Vec<usize>,
     /// Map from order ID to index in orders vector
     order_id_to_index: HashMap<OrderId, usize>,
     
     /// All limit levels
-    limits: Vec<Option<Limit>>,
+    pub(crate) limits: Vec<Option<Limit>>,
     /// Free indices in the limits vector
     free_limit_indices: Vec<usize>,
-    /// Map from price to index in limits vector
-    price_to_limit_index: HashMap<Price, usize>,
-    
-    /// Root of buy tree (highest prices first)
-    buy_tree_root: Option<usize>,
-    /// Root of sell tree (lowest prices first)  
-    sell_tree_root: Option<usize>,
+    /// Buy levels by price (best is highest)
+    bid_levels: I,
+    /// Sell levels by price (best is lowest)
+    ask_levels: I,
     
     /// Best bid (highest buy price)
-    best_bid_index: Option<usize>,
+    pub(crate) best_bid_index: Option<usize>,
     /// Best ask (lowest sell price)
-    best_ask_index: Option<usize>,
+    pub(crate) best_ask_index: Option<usize>,
+    /// Best bid level with displayed orders
+    best_displayed_bid_index: Option<usize>,
+    /// Best ask level with displayed orders
+    best_displayed_ask_index: Option<usize>,
     
     /// Current timestamp for operations
-    current_time: Timestamp,
+    pub(crate) current_time: Timestamp,
+    /// Tick, lot and price band rules checked on entry and modification
+    pub(crate) config: InstrumentConfig,
+    /// Trading phase deciding which operations are accepted
+    pub(crate) phase: TradingPhase,
+    /// Tie-break price of the running call auction
+    pub(crate) auction_reference_price: Option<Price>,
+    /// Source of randomized iceberg peak sizes
+    peak_rng: StdRng,
+    /// Price of the most recent trade
+    pub(crate) last_trade_price: Option<Price>,
+    /// Stop orders waiting for their trigger
+    pub(crate) stops: StopBook,
+
+    /// Whether mutations are recorded as events
+    events_enabled: bool,
+    /// Recorded events not yet drained
+    events: Vec<BookEvent>,
 }
 
 impl OrderBook {
     /// Create a new empty orderbook
     pub fn new() -> Self {
-        Self {
-            orders: Vec::new(),
-            free_order_indices: Vec::new(),
-            order_id_to_index: HashMap::new(),
-            limits: Vec::new(),
-            free_limit_indices: Vec::new(),
-            price_to_limit_index: HashMap::new(),
-            buy_tree_root: None,
-            sell_tree_root: None,
-            best_bid_index: None,
-            best_ask_index: None,
-            current_time: 0,
-        }
+        Self::with_backend(InstrumentConfig::default())
     }
 
     /// Create a new orderbook with pre-allocated capacity
     pub fn with_capacity(order_capacity: usize, limit_capacity: usize) -> Self {
+        Self::with_backend_and_capacity(InstrumentConfig::default(), order_capacity, limit_capacity)
+    }
+
+    /// Create a new empty orderbook with the given instrument rules
+    pub fn with_config(config: InstrumentConfig) -> Self {
+        Self::with_backend(config)
+    }
+}
+
+impl<I: PriceLevelIndex> OrderBook<I> {
+    /// Create a new empty orderbook using price-level index `I`
+    ///
+    /// ```
+    /// use hft_orderbook::{InstrumentConfig, LadderIndex, OrderBook};
+    ///
+    /// let config = InstrumentConfig::new(5, 1).with_price_band(9_000, 11_000);
+    /// let book = OrderBook::<LadderIndex>::with_backend(config);
+    /// assert_eq!(book.total_levels(), 0);
+    /// ```
+    pub fn with_backend(config: InstrumentConfig) -> Self {
+        Self::with_backend_and_capacity(config, 0, 0)
+    }
+
+    /// Create a new orderbook using price-level index `I`, with pre-allocated capacity
+    pub fn with_backend_and_capacity(
+        config: InstrumentConfig,
+        order_capacity: usize,
+        limit_capacity: usize,
+    ) -> Self {
         Self {
             orders: Vec::with_capacity(order_capacity),
             free_order_indices: Vec::new(),
             order_id_to_index: HashMap::with_capacity(order_capacity),
             limits: Vec::with_capacity(limit_capacity),
             free_limit_indices: Vec::new(),
-            price_to_limit_index: HashMap::with_capacity(limit_capacity),
-            buy_tree_root: None,
-            sell_tree_root: None,
+            bid_levels: I::for_config(&config),
+            ask_levels: I::for_config(&config),
             best_bid_index: None,
             best_ask_index: None,
+            best_displayed_bid_index: None,
+            best_displayed_ask_index: None,
             current_time: 0,
+            config,
+            phase: TradingPhase::Continuous,
+            auction_reference_price: None,
+            peak_rng: StdRng::seed_from_u64(0),
+            last_trade_price: None,
+            stops: StopBook::default(),
+            events_enabled: false,
+            events: Vec::new(),
         }
     }
 
+    /// Get the instrument rules
+    pub fn config(&self) -> &InstrumentConfig {
+        &self.config
+    }
+
+    /// Replace the instrument rules
+    ///
+    /// Only affects orders entered or modified afterwards; resting orders are
+    /// kept. The price-level indexes are rebuilt for the new rules, and the
+    /// book is left unchanged if a resting level does not fit them (possible
+    /// only with bounded indexes such as `LadderIndex`).
+    pub fn set_config(&mut self, config: InstrumentConfig) -> Result<()> {
+        let mut bid_levels = I::for_config(&config);
+        let mut ask_levels = I::for_config(&config);
+        for (levels, best) in [(&mut bid_levels, self.best_bid_index), (&mut ask_levels, self.best_ask_index)] {
+            let mut level = best;
+            while let Some(limit_idx) = level {
+                let price = self.limits[limit_idx].as_ref().unwrap().price;
+                if !levels.accepts(price) {
+                    return Err(OrderBookError::PriceOutOfBand(price));
+                }
+                levels.insert(price, limit_idx);
+                level = self.next_worse_level(limit_idx);
+            }
+        }
+
+        self.bid_levels = bid_levels;
+        self.ask_levels = ask_levels;
+        self.config = config;
+        Ok(())
+    }
+
     /// Set the current timestamp
     pub fn set_time(&mut self, timestamp: Timestamp) {
         self.current_time = timestamp;
@@ -94,58 +181,175 @@ impl OrderBook {
         self.current_time
     }
 
-    /// Get the best bid price and quantity
+    /// Get the trading phase
+    pub fn phase(&self) -> TradingPhase {
+        self.phase
+    }
+
+    /// Move the book to another trading phase
+    ///
+    /// The change is stamped with the time given to `set_time` and recorded as
+    /// a `PhaseChanged` event. A closed book only reopens through `PreOpen`,
+    /// and `Continuous` can only be entered while the book is not crossed;
+    /// uncross it with a call auction first. Setting the current phase again
+    /// does nothing.
+    pub fn set_phase(&mut self, phase: TradingPhase) -> Result<()> {
+        if phase == self.phase {
+            return Ok(());
+        }
+        if !self.phase.can_transition_to(phase) || (phase == TradingPhase::Continuous && self.is_crossed()) {
+            return Err(OrderBookError::NotAllowedInPhase(self.phase));
+        }
+        self.enter_phase(phase);
+        Ok(())
+    }
+
+    /// Seed the generator of randomized iceberg peak sizes
+    ///
+    /// Books start from a fixed seed, so replenishments are reproducible.
+    pub fn set_peak_seed(&mut self, seed: u64) {
+        self.peak_rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Enable or disable recording of `BookEvent`s for every mutation
+    ///
+    /// Recording is off by default. Disabling it discards any undrained events.
+    pub fn set_events_enabled(&mut self, enabled: bool) {
+        self.events_enabled = enabled;
+        if !enabled {
+            self.events.clear();
+        }
+    }
+
+    /// Check whether events are being recorded
+    pub fn events_enabled(&self) -> bool {
+        self.events_enabled
+    }
+
+    /// Take all events recorded since the last drain, oldest first
+    pub fn drain_events(&mut self) -> Vec<BookEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Get the best bid price and displayed quantity
+    ///
+    /// Levels holding only hidden orders are not shown.
     pub fn best_bid(&self) -> Option<(Price, Quantity)> {
-        self.best_bid_index
-            .and_then(|idx| self.limits[idx].as_ref())
-            .map(|limit| (limit.price, limit.size))
+        self.best_displayed_bid_index.map(|idx| self.level_at(idx))
     }
 
-    /// Get the best ask price and quantity
+    /// Get the best ask price and displayed quantity
+    ///
+    /// Levels holding only hidden orders are not shown.
     pub fn best_ask(&self) -> Option<(Price, Quantity)> {
-        self.best_ask_index
-            .and_then(|idx| self.limits[idx].as_ref())
-            .map(|limit| (limit.price, limit.size))
+        self.best_displayed_ask_index.map(|idx| self.level_at(idx))
+    }
+
+    /// Price and displayed quantity of a limit
+    fn level_at(&self, limit_idx: usize) -> (Price, Quantity) {
+        let limit = self.limits[limit_idx].as_ref().unwrap();
+        (limit.price, limit.size)
+    }
+
+    /// Get the best bid price and quantity in decimal notation
+    pub fn best_bid_decimal(&self) -> Option<(Decimal, Decimal)> {
+        self.best_bid().map(|level| self.decimal_level(level))
+    }
+
+    /// Get the best ask price and quantity in decimal notation
+    pub fn best_ask_decimal(&self) -> Option<(Decimal, Decimal)> {
+        self.best_ask().map(|level| self.decimal_level(level))
     }
 
     /// Get the spread (difference between best ask and best bid)
+    ///
+    /// Negative when the book is crossed.
     pub fn spread(&self) -> Option<Price> {
         match (self.best_ask(), self.best_bid()) {
-            (Some((ask_price, _)), Some((bid_price, _))) => {
-                if ask_price > bid_price {
-                    Some(ask_price - bid_price)
-                } else {
-                    Some(0)
-                }
-            }
+            (Some((ask_price, _)), Some((bid_price, _))) => Some(ask_price - bid_price),
             _ => None,
         }
     }
 
+    /// Check if the best bid is at or above the best ask, counting hidden orders
+    ///
+    /// Only possible outside continuous trading, or through `add_order` and
+    /// `amend_order`, which never match.
+    pub fn is_crossed(&self) -> bool {
+        match (self.bids_with_hidden().next(), self.asks_with_hidden().next()) {
+            (Some(bid), Some(ask)) => bid.price >= ask.price,
+            _ => false,
+        }
+    }
+
     /// Get the mid price (average of best bid and ask)
+    ///
+    /// Odd sums round towards negative infinity, so the result is consistent
+    /// on both sides of zero.
     pub fn mid_price(&self) -> Option<Price> {
         match (self.best_ask(), self.best_bid()) {
             (Some((ask_price, _)), Some((bid_price, _))) => {
-                Some((ask_price + bid_price) / 2)
+                Some((ask_price as i128 + bid_price as i128).div_euclid(2) as Price)
             }
             _ => None,
         }
     }
 
-    /// Get total volume at a specific price level
+    /// Get the price of the most recent trade, including auction uncrosses
+    pub fn last_trade_price(&self) -> Option<Price> {
+        self.last_trade_price
+    }
+
+    /// Get displayed volume at a specific price level
+    ///
+    /// Levels holding only hidden orders are not shown.
+    ///
+    /// If both sides have a level at this price (locked or crossed book),
+    /// their volumes are summed.
     pub fn volume_at_price(&self, price: Price) -> Option<Quantity> {
-        self.price_to_limit_index
-            .get(&price)
-            .and_then(|&idx| self.limits[idx].as_ref())
-            .map(|limit| limit.size)
+        Self::combine_sides(
+            self.volume_at_price_for_side(Side::Buy, price),
+            self.volume_at_price_for_side(Side::Sell, price),
+        )
     }
 
-    /// Get number of orders at a specific price level
+    /// Get number of displayed orders at a specific price level
+    ///
+    /// If both sides have a level at this price (locked or crossed book),
+    /// their order counts are summed.
     pub fn orders_at_price(&self, price: Price) -> Option<usize> {
-        self.price_to_limit_index
-            .get(&price)
-            .and_then(|&idx| self.limits[idx].as_ref())
-            .map(|limit| limit.order_count)
+        Self::combine_sides(
+            self.orders_at_price_for_side(Side::Buy, price),
+            self.orders_at_price_for_side(Side::Sell, price),
+        )
+    }
+
+    /// Get displayed volume at a specific price level on one side of the book
+    pub fn volume_at_price_for_side(&self, side: Side, price: Price) -> Option<Quantity> {
+        self.limit_at(side, price).map(|limit| limit.size).filter(|&size| size > 0)
+    }
+
+    /// Get number of displayed orders at a specific price level on one side of the book
+    pub fn orders_at_price_for_side(&self, side: Side, price: Price) -> Option<usize> {
+        self.limit_at(side, price).map(Limit::displayed_order_count).filter(|&count| count > 0)
+    }
+
+    /// Get total volume at a specific price level, including hidden orders and
+    /// iceberg reserves
+    ///
+    /// If both sides have a level at this price (locked or crossed book),
+    /// their volumes are summed.
+    pub fn volume_at_price_with_hidden(&self, price: Price) -> Option<Quantity> {
+        Self::combine_sides(
+            self.volume_at_price_with_hidden_for_side(Side::Buy, price),
+            self.volume_at_price_with_hidden_for_side(Side::Sell, price),
+        )
+    }
+
+    /// Get total volume at a specific price level on one side of the book,
+    /// including hidden orders and iceberg reserves
+    pub fn volume_at_price_with_hidden_for_side(&self, side: Side, price: Price) -> Option<Quantity> {
+        self.limit_at(side, price).map(Limit::total_size)
     }
 
     /// Check if an order exists
@@ -160,6 +364,29 @@ impl OrderBook {
             .and_then(|&idx| self.orders[idx].as_ref())
     }
 
+    /// Iterate the orders resting at a price level in FIFO (fill) order
+    ///
+    /// Yields nothing if there is no level at this price on the given side.
+    pub fn orders_at_level(&self, side: Side, price: Price) -> LevelOrders<'_> {
+        let head = self.limit_at(side, price).and_then(|limit| limit.head_order_index);
+        LevelOrders::new(&self.orders, head)
+    }
+
+    /// Get the total quantity queued ahead of an order at its price level
+    ///
+    /// Returns `None` if the order is not in the book.
+    pub fn queue_position(&self, order_id: OrderId) -> Option<Quantity> {
+        let order_idx = *self.order_id_to_index.get(&order_id)?;
+        let mut ahead = 0;
+        let mut prev = self.orders[order_idx].as_ref()?.prev_order_index;
+        while let Some(idx) = prev {
+            let order = self.orders[idx].as_ref()?;
+            ahead += order.quantity;
+            prev = order.prev_order_index;
+        }
+        Some(ahead)
+    }
+
     /// Get total number of orders in the book
     pub fn total_orders(&self) -> usize {
         self.order_id_to_index.len()
@@ -167,27 +394,25 @@ impl OrderBook {
 
     /// Get total number of price levels
     pub fn total_levels(&self) -> usize {
-        self.price_to_limit_index.len()
+        self.bid_levels.len() + self.ask_levels.len()
     }
 
     /// Add a new order to the book
     ///
     /// This is a pure data structure operation - no matching logic.
     /// The order is simply added to the appropriate price level.
+    /// Only limit orders can rest; use `submit` for other order types and
+    /// for stop orders.
     pub fn add_order(&mut self, mut order: Order) -> Result<()> {
-        // Validate order
-        if order.price == 0 {
-            return Err(OrderBookError::InvalidPrice(order.price));
-        }
-        if order.quantity == 0 {
-            return Err(OrderBookError::InvalidQuantity(order.quantity));
-        }
-        if self.contains_order(order.id) {
+        self.validate_resting_order(&order)?;
+        if self.contains_order(order.id) || self.stops.contains(order.id) {
             return Err(OrderBookError::OrderAlreadyExists(order.id));
         }
 
         order.event_time = self.current_time;
+        let bbo_before = self.bbo_before();
         self.add_order_to_book(order)?;
+        self.emit_bbo_change(bbo_before);
         Ok(())
     }
 
@@ -207,92 +432,438 @@ impl OrderBook {
         Ok(())
     }
 
-    /// Remove an order from the book
+    /// Remove an order from the book, or a parked stop order
     pub fn remove_order(&mut self, order_id: OrderId) -> Result<Order> {
-        let order_idx = self.order_id_to_index
-            .get(&order_id)
-            .copied()
-            .ok_or(OrderBookError::OrderNotFound(order_id))?;
+        self.check_phase(self.phase.accepts_cancels())?;
+        let Some(&order_idx) = self.order_id_to_index.get(&order_id) else {
+            let mut stop = self.stops.remove(order_id).ok_or(OrderBookError::OrderNotFound(order_id))?;
+            stop.cancel(self.current_time);
+            return Ok(stop);
+        };
 
+        let bbo_before = self.bbo_before();
+        let cancelled_order = self.cancel_resting_order(order_idx);
+        self.emit_bbo_change(bbo_before);
+
+        Ok(cancelled_order)
+    }
+
+    /// Cancel a resting order and remove it from the book, without a BBO event
+    pub(crate) fn cancel_resting_order(&mut self, order_idx: usize) -> Order {
         let order = self.orders[order_idx].as_mut().unwrap();
+        let order_id = order.id;
         let limit_idx = order.parent_limit_index.unwrap();
 
         // Mark order as cancelled
         order.cancel(self.current_time);
         let cancelled_order = order.clone();
 
+        if !cancelled_order.hidden {
+            let size_before = self.limits[limit_idx].as_ref().unwrap().size;
+            self.emit(BookEvent::OrderCancelled {
+                order_id,
+                quantity: cancelled_order.quantity,
+                level: LevelDelta::new(
+                    cancelled_order.side,
+                    cancelled_order.price,
+                    size_before,
+                    size_before - cancelled_order.quantity,
+                ),
+                timestamp: self.current_time,
+            });
+        }
+
         // Remove from limit
-        self.remove_order_from_limit(order_idx, limit_idx)?;
+        self.remove_order_from_limit(order_idx, limit_idx)
+            .expect("resting order should be linked to its limit");
 
-        Ok(cancelled_order)
+        cancelled_order
     }
 
     /// Update an order's quantity
+    ///
+    /// The order always keeps its queue position; use `amend_order` for
+    /// exchange-style priority rules and price changes.
     pub fn update_order(&mut self, order_id: OrderId, new_quantity: Quantity) -> Result<()> {
-        if new_quantity == 0 {
-            return Err(OrderBookError::InvalidQuantity(new_quantity));
-        }
+        self.check_phase(self.phase.accepts_modifications())?;
+        self.config.validate_quantity(new_quantity)?;
 
         let order_idx = self.order_id_to_index
             .get(&order_id)
             .copied()
             .ok_or(OrderBookError::OrderNotFound(order_id))?;
 
+        let bbo_before = self.bbo_before();
+        self.resize_resting_order(order_idx, new_quantity)?;
+        self.emit_bbo_change(bbo_before);
+
+        Ok(())
+    }
+
+    /// Change a resting order's quantity in place, without a BBO event
+    ///
+    /// The order keeps its queue position. For an iceberg order
+    /// `new_quantity` is the new total; the event reports displayed quantities.
+    pub(crate) fn resize_resting_order(&mut self, order_idx: usize, new_quantity: Quantity) -> Result<()> {
         let order = self.orders[order_idx].as_mut().unwrap();
+        let order_id = order.id;
         let limit_idx = order.parent_limit_index.unwrap();
-        let old_quantity = order.quantity;
+        let (old_quantity, old_displayed, old_undisplayed) =
+            (order.quantity, order.displayed_quantity(), order.undisplayed_quantity());
 
         // Update order quantity
         if !order.update_quantity(new_quantity, self.current_time) {
             return Err(OrderBookError::InvalidQuantity(new_quantity));
         }
+        let (quantity, hidden) = (order.quantity, order.hidden);
 
         // Update limit statistics
-        self.limits[limit_idx].as_mut().unwrap()
-            .update_order_stats(old_quantity, order.quantity);
+        let limit = self.limits[limit_idx].as_mut().unwrap();
+        let size_before = limit.size;
+        limit.update_order_stats(old_displayed, order.displayed_quantity());
+        limit.update_hidden_stats(old_undisplayed, order.undisplayed_quantity());
+        let level = LevelDelta::new(limit.side, limit.price, size_before, limit.size);
+
+        if !hidden {
+            self.emit(BookEvent::OrderModified {
+                order_id,
+                old_quantity,
+                new_quantity: quantity,
+                priority_lost: false,
+                old_level: level,
+                new_level: level,
+                timestamp: self.current_time,
+            });
+        }
 
         Ok(())
     }
 
+    /// Amend an order's price and/or quantity (cancel/replace semantics)
+    ///
+    /// Follows standard exchange priority rules:
+    /// - a quantity decrease at the same price keeps the order's queue position
+    /// - a quantity increase or any price change loses time priority: the order
+    ///   moves to the tail of its (possibly new) limit and its entry time is reset;
+    ///   a displayed order still queues ahead of the hidden ones
+    ///
+    /// Like `update_order`, this is a pure data structure operation - an amended
+    /// price that crosses the opposite side is not matched.
+    pub fn amend_order(
+        &mut self,
+        order_id: OrderId,
+        new_price: Price,
+        new_quantity: Quantity,
+    ) -> Result<AmendResult> {
+        self.check_phase(self.phase.accepts_modifications())?;
+        self.config.validate_price(new_price)?;
+        self.config.validate_quantity(new_quantity)?;
+
+        let order_idx = self.order_id_to_index
+            .get(&order_id)
+            .copied()
+            .ok_or(OrderBookError::OrderNotFound(order_id))?;
+
+        let order = self.orders[order_idx].as_ref().unwrap();
+        let limit_idx = order.parent_limit_index.unwrap();
+        let side = order.side;
+        let old_price = order.price;
+        let old_quantity = order.total_quantity();
+        let (old_displayed, old_undisplayed) = (order.displayed_quantity(), order.undisplayed_quantity());
+        let hidden = order.hidden;
+        if !self.price_index(side).accepts(new_price) {
+            return Err(OrderBookError::PriceOutOfBand(new_price));
+        }
+
+        let price_changed = new_price != old_price;
+        let priority_lost = price_changed || new_quantity > old_quantity;
+        let old_limit = self.limits[limit_idx].as_ref().unwrap();
+        let already_tail = old_limit.tail_order_index == Some(order_idx);
+        let old_size_before = old_limit.size;
+        let bbo_before = self.bbo_before();
+        let now = self.current_time;
+
+        let (old_level, new_level) = if !price_changed && (!priority_lost || already_tail) {
+            // Stays where it is in the queue; only the size (and maybe entry time) changes
+            let order = self.orders[order_idx].as_mut().unwrap();
+            order.update_quantity(new_quantity, now);
+            if priority_lost {
+                order.entry_time = now;
+            }
+            let limit = self.limits[limit_idx].as_mut().unwrap();
+            limit.update_order_stats(old_displayed, order.displayed_quantity());
+            limit.update_hidden_stats(old_undisplayed, order.undisplayed_quantity());
+
+            let level = LevelDelta::new(side, old_price, old_size_before, limit.size);
+            (level, level)
+        } else {
+            self.unlink_order_from_limit(order_idx, limit_idx)?;
+
+            let order = self.orders[order_idx].as_mut().unwrap();
+            order.price = new_price;
+            order.update_quantity(new_quantity, now);
+            order.entry_time = now;
+            let new_displayed = order.displayed_quantity();
+
+            let new_limit_idx = self.get_or_create_limit(new_price, side)?;
+            self.add_order_to_limit(order_idx, new_limit_idx)?;
+            self.update_best_prices(new_limit_idx, side);
+
+            let new_size_after = self.limits[new_limit_idx].as_ref().unwrap().size;
+            let new_level = LevelDelta::new(side, new_price, new_size_after - new_displayed, new_size_after);
+            let old_level = if price_changed {
+                LevelDelta::new(side, old_price, old_size_before, old_size_before - old_displayed)
+            } else {
+                new_level
+            };
+            (old_level, new_level)
+        };
+
+        if !hidden {
+            self.emit(BookEvent::OrderModified {
+                order_id,
+                old_quantity: old_displayed,
+                new_quantity: self.orders[order_idx].as_ref().unwrap().quantity,
+                priority_lost,
+                old_level,
+                new_level,
+                timestamp: now,
+            });
+        }
+        self.emit_bbo_change(bbo_before);
+
+        Ok(AmendResult {
+            order_id,
+            old_price,
+            new_price,
+            old_quantity,
+            new_quantity,
+            priority_lost,
+        })
+    }
+
+    /// Iterate displayed bid levels from the highest price down
+    pub fn bids(&self) -> Levels<'_, I> {
+        Levels::new(self, self.best_displayed_bid_index, false)
+    }
+
+    /// Iterate displayed ask levels from the lowest price up
+    pub fn asks(&self) -> Levels<'_, I> {
+        Levels::new(self, self.best_displayed_ask_index, false)
+    }
+
+    /// Iterate bid levels from the highest price down, including levels
+    /// holding only hidden orders
+    pub fn bids_with_hidden(&self) -> Levels<'_, I> {
+        Levels::new(self, self.best_bid_index, true)
+    }
+
+    /// Iterate ask levels from the lowest price up, including levels holding
+    /// only hidden orders
+    pub fn asks_with_hidden(&self) -> Levels<'_, I> {
+        Levels::new(self, self.best_ask_index, true)
+    }
+
     /// Get price levels (similar to Python's levels method)
-    /// Returns a vector of (price, quantity) tuples for each side
+    /// Returns a vector of (price, quantity) tuples for each side,
+    /// bids highest first and asks lowest first
+    #[allow(clippy::type_complexity)]
     pub fn get_levels(&self, depth: Option<usize>) -> (Vec<(Price, Quantity)>, Vec<(Price, Quantity)>) {
-        let mut bids = Vec::new();
-        let mut asks = Vec::new();
+        let depth = depth.unwrap_or(usize::MAX);
+        let bids = self.bids().take(depth).map(|limit| (limit.price, limit.size)).collect();
+        let asks = self.asks().take(depth).map(|limit| (limit.price, limit.size)).collect();
+        (bids, asks)
+    }
+
+    /// Get price levels with their total volume, including hidden orders and
+    /// iceberg reserves
+    ///
+    /// Same layout as `get_levels`, but levels holding only hidden orders are
+    /// listed too.
+    #[allow(clippy::type_complexity)]
+    pub fn get_levels_with_hidden(&self, depth: Option<usize>) -> (Vec<(Price, Quantity)>, Vec<(Price, Quantity)>) {
+        let depth = depth.unwrap_or(usize::MAX);
+        let bids = self.bids_with_hidden().take(depth).map(|limit| (limit.price, limit.total_size())).collect();
+        let asks = self.asks_with_hidden().take(depth).map(|limit| (limit.price, limit.total_size())).collect();
+        (bids, asks)
+    }
 
-        // Collect all price levels
-        let mut prices: Vec<Price> = self.price_to_limit_index.keys().copied().collect();
-        prices.sort();
+    // Internal helper methods
 
-        // Separate bids and asks based on best bid/ask
-        let _mid_price = self.mid_price().unwrap_or(0);
+    /// Record an event if recording is enabled
+    pub(crate) fn emit(&mut self, event: BookEvent) {
+        if self.events_enabled {
+            self.events.push(event);
+        }
+    }
 
-        for price in prices {
-            if let Some(limit) = self.price_to_limit_index.get(&price)
-                .and_then(|&idx| self.limits[idx].as_ref()) {
+    /// Current best bid and ask
+    pub(crate) fn bbo(&self) -> Bbo {
+        (self.best_bid(), self.best_ask())
+    }
 
-                if limit.side == Side::Buy {
-                    bids.push((price, limit.size));
-                } else {
-                    asks.push((price, limit.size));
-                }
-            }
+    /// Top of book to compare against after a mutation, or `None` when events
+    /// are not recorded
+    ///
+    /// Finding the best displayed levels may skip over hidden-only levels, so
+    /// this is only worth doing when a `BboChanged` event can follow.
+    pub(crate) fn bbo_before(&self) -> Option<Bbo> {
+        self.events_enabled.then(|| self.bbo())
+    }
+
+    /// Record a `BboChanged` event if the top of book differs from `before`
+    pub(crate) fn emit_bbo_change(&mut self, before: Option<Bbo>) {
+        let Some(before) = before else {
+            return;
+        };
+        let (best_bid, best_ask) = self.bbo();
+        if (best_bid, best_ask) != before {
+            self.emit(BookEvent::BboChanged {
+                best_bid,
+                best_ask,
+                timestamp: self.current_time,
+            });
         }
+    }
 
-        // Sort bids descending (highest first), asks ascending (lowest first)
-        bids.sort_by(|a, b| b.0.cmp(&a.0));
-        asks.sort_by(|a, b| a.0.cmp(&b.0));
+    /// Check that `add_order` would accept an order, apart from its ID
+    pub(crate) fn validate_resting_order(&self, order: &Order) -> Result<()> {
+        self.check_phase(self.phase.accepts_orders())?;
+        if !order.order_type.can_rest() || order.is_stop() {
+            return Err(OrderBookError::InvalidOrderType(order.order_type));
+        }
+        self.config.validate_price(order.price)?;
+        self.check_price_fits(order.side, order.price)?;
+        self.validate_order_quantity(order)
+    }
 
-        // Apply depth limit if specified
-        if let Some(d) = depth {
-            bids.truncate(d);
-            asks.truncate(d);
+    /// Check that a level at `price` exists on `side` or can be created
+    pub(crate) fn check_price_fits(&self, side: Side, price: Price) -> Result<()> {
+        let levels = self.price_index(side);
+        if levels.get(price).is_none() && !levels.accepts(price) {
+            return Err(OrderBookError::PriceOutOfBand(price));
         }
+        Ok(())
+    }
 
-        (bids, asks)
+    /// Check an incoming order's quantity, and its peak if it is an iceberg
+    ///
+    /// An order cannot be both hidden and an iceberg, as it would have nothing
+    /// to display.
+    pub(crate) fn validate_order_quantity(&self, order: &Order) -> Result<()> {
+        if order.hidden && order.is_iceberg() {
+            return Err(OrderBookError::InvalidOrderType(order.order_type));
+        }
+        if order.is_iceberg() {
+            self.config.validate_quantity(order.peak_size)?;
+        }
+        self.config.validate_quantity(order.total_quantity())
     }
 
-    // Internal helper methods
+    /// Refill an iceberg order's displayed quantity from its reserve
+    ///
+    /// Called once the displayed slice has been filled. The new slice goes to
+    /// the back of the same limit's queue with a fresh entry time and is
+    /// recorded as `OrderAdded`.
+    pub(crate) fn replenish_iceberg(&mut self, order_idx: usize, limit_idx: usize) {
+        let now = self.current_time;
+        let peak = self.next_peak_size(order_idx);
+        let order = self.orders[order_idx].as_mut().unwrap();
+        debug_assert!(order.quantity == 0 && order.hidden_quantity > 0);
+        let slice = peak.min(order.hidden_quantity);
+        order.hidden_quantity -= slice;
+        order.quantity = slice;
+        order.entry_time = now;
+        order.event_time = now;
+        let (order_id, exchange_id) = (order.id, order.exchange_id);
+
+        let limit = self.limits[limit_idx].as_mut().unwrap();
+        let size_before = limit.size;
+        limit.update_order_stats(0, slice);
+        limit.update_hidden_stats(slice, 0);
+        let level = LevelDelta::new(limit.side, limit.price, size_before, limit.size);
+        self.requeue_order(order_idx, limit_idx);
+
+        self.emit(BookEvent::OrderAdded {
+            order_id,
+            quantity: slice,
+            entry_time: now,
+            exchange_id,
+            level,
+            timestamp: now,
+        });
+    }
+
+    /// Size of an iceberg order's next displayed slice
+    ///
+    /// With a peak variance the size is drawn uniformly around the peak and
+    /// rounded down to a whole number of lots, but never below one lot.
+    fn next_peak_size(&mut self, order_idx: usize) -> Quantity {
+        let order = self.orders[order_idx].as_ref().unwrap();
+        let (peak, variance) = (order.peak_size, order.peak_variance);
+        if variance == 0 {
+            return peak;
+        }
+
+        let size = self.peak_rng.gen_range(peak.saturating_sub(variance)..=peak.saturating_add(variance));
+        let lot = self.config.lot_size.max(1);
+        (size - size % lot).max(lot)
+    }
+
+    /// Reject an operation the current trading phase does not accept
+    pub(crate) fn check_phase(&self, allowed: bool) -> Result<()> {
+        if allowed {
+            Ok(())
+        } else {
+            Err(OrderBookError::NotAllowedInPhase(self.phase))
+        }
+    }
+
+    /// Switch trading phase unconditionally, recording `PhaseChanged`
+    ///
+    /// Any call auction reference price is cleared.
+    pub(crate) fn enter_phase(&mut self, phase: TradingPhase) {
+        let from = self.phase;
+        self.phase = phase;
+        self.auction_reference_price = None;
+        self.emit(BookEvent::PhaseChanged { from, to: phase, timestamp: self.current_time });
+    }
+
+    /// Price-level index for one side of the book
+    fn price_index(&self, side: Side) -> &I {
+        match side {
+            Side::Buy => &self.bid_levels,
+            Side::Sell => &self.ask_levels,
+        }
+    }
+
+    /// Mutable price-level index for one side of the book
+    fn price_index_mut(&mut self, side: Side) -> &mut I {
+        match side {
+            Side::Buy => &mut self.bid_levels,
+            Side::Sell => &mut self.ask_levels,
+        }
+    }
+
+    /// Get the limit at a price on one side of the book
+    fn limit_at(&self, side: Side, price: Price) -> Option<&Limit> {
+        self.price_index(side)
+            .get(price)
+            .and_then(|idx| self.limits[idx].as_ref())
+    }
+
+    /// Convert a (price, quantity) pair to decimals using the instrument scales
+    fn decimal_level(&self, (price, quantity): (Price, Quantity)) -> (Decimal, Decimal) {
+        (self.config.price_decimal(price), self.config.quantity_decimal(quantity))
+    }
+
+    /// Sum per-side level statistics, treating a missing level as absent
+    fn combine_sides<T: std::ops::Add<Output = T>>(buy: Option<T>, sell: Option<T>) -> Option<T> {
+        match (buy, sell) {
+            (Some(buy), Some(sell)) => Some(buy + sell),
+            (buy, sell) => buy.or(sell),
+        }
+    }
 
     /// Allocate a new order index
     fn allocate_order_index(&mut self) -> usize {
@@ -329,117 +900,213 @@ impl OrderBook {
     }
 
     /// Add an order to the book (internal implementation)
-    fn add_order_to_book(&mut self, order: Order) -> Result<()> {
+    pub(crate) fn add_order_to_book(&mut self, order: Order) -> Result<()> {
         let order_idx = self.allocate_order_index();
         let order_id = order.id;
         let price = order.price;
         let side = order.side;
         let quantity = order.quantity;
+        let hidden = order.hidden;
+        let entry_time = order.entry_time;
+        let exchange_id = order.exchange_id;
+
+        // Get or create limit level, before anything is stored
+        let limit_idx = match self.get_or_create_limit(price, side) {
+            Ok(limit_idx) => limit_idx,
+            Err(e) => {
+                self.free_order_index(order_idx);
+                return Err(e);
+            }
+        };
 
-        // Store the order first
         self.orders[order_idx] = Some(order);
         self.order_id_to_index.insert(order_id, order_idx);
 
-        // Get or create limit level
-        let limit_idx = self.get_or_create_limit(price, side)?;
-
         // Add order to the limit's linked list
-        self.add_order_to_limit(order_idx, limit_idx, quantity)?;
+        self.add_order_to_limit(order_idx, limit_idx)?;
 
         // Update best bid/ask if necessary
         self.update_best_prices(limit_idx, side);
 
+        if !hidden {
+            let size_after = self.limits[limit_idx].as_ref().unwrap().size;
+            self.emit(BookEvent::OrderAdded {
+                order_id,
+                quantity,
+                entry_time,
+                exchange_id,
+                level: LevelDelta::new(side, price, size_after - quantity, size_after),
+                timestamp: self.current_time,
+            });
+        }
+
         Ok(())
     }
 
     /// Get or create a limit level at the specified price
     fn get_or_create_limit(&mut self, price: Price, side: Side) -> Result<usize> {
-        if let Some(&limit_idx) = self.price_to_limit_index.get(&price) {
+        if let Some(limit_idx) = self.price_index(side).get(price) {
             Ok(limit_idx)
         } else {
+            if !self.price_index(side).accepts(price) {
+                return Err(OrderBookError::PriceOutOfBand(price));
+            }
+
             // Create new limit
             let limit_idx = self.allocate_limit_index();
-            let limit = Limit::new(price, side);
-            self.limits[limit_idx] = Some(limit);
-            self.price_to_limit_index.insert(price, limit_idx);
-
-            // Add to appropriate tree based on order side
-            match side {
-                Side::Buy => {
-                    self.buy_tree_root = Some(self.insert_into_tree(self.buy_tree_root, limit_idx));
-                }
-                Side::Sell => {
-                    self.sell_tree_root = Some(self.insert_into_tree(self.sell_tree_root, limit_idx));
-                }
+            let mut limit = Limit::new(price, side);
+
+            // Splice between the neighbouring levels; this is the only place
+            // the index is searched, later hops follow the links
+            let levels = self.price_index(side);
+            limit.lower_limit_index = levels.next_lower(price);
+            limit.higher_limit_index = levels.next_higher(price);
+            if let Some(lower) = limit.lower_limit_index {
+                self.limits[lower].as_mut().unwrap().higher_limit_index = Some(limit_idx);
+            }
+            if let Some(higher) = limit.higher_limit_index {
+                self.limits[higher].as_mut().unwrap().lower_limit_index = Some(limit_idx);
             }
 
+            self.limits[limit_idx] = Some(limit);
+            self.price_index_mut(side).insert(price, limit_idx);
+
             Ok(limit_idx)
         }
     }
 
     /// Add an order to a limit's linked list
-    fn add_order_to_limit(&mut self, order_idx: usize, limit_idx: usize, quantity: Quantity) -> Result<()> {
-        // Get the tail index before borrowing
-        let tail_idx = self.limits[limit_idx].as_ref().unwrap().tail_order_index;
-
+    fn add_order_to_limit(&mut self, order_idx: usize, limit_idx: usize) -> Result<()> {
         // Update order's parent limit
-        self.orders[order_idx].as_mut().unwrap().parent_limit_index = Some(limit_idx);
-
-        // Add to tail of linked list (FIFO)
-        if let Some(tail_idx) = tail_idx {
-            // List is not empty - update the current tail to point to new order
-            self.orders[tail_idx].as_mut().unwrap().next_order_index = Some(order_idx);
-            self.orders[order_idx].as_mut().unwrap().prev_order_index = Some(tail_idx);
-            self.limits[limit_idx].as_mut().unwrap().tail_order_index = Some(order_idx);
-        } else {
-            // List is empty
-            let limit = self.limits[limit_idx].as_mut().unwrap();
-            limit.head_order_index = Some(order_idx);
-            limit.tail_order_index = Some(order_idx);
-        }
+        let order = self.orders[order_idx].as_mut().unwrap();
+        order.parent_limit_index = Some(limit_idx);
+        let (displayed, undisplayed, hidden) = (order.displayed_quantity(), order.undisplayed_quantity(), order.hidden);
+
+        self.link_order(order_idx, limit_idx);
 
         // Update limit statistics
-        self.limits[limit_idx].as_mut().unwrap().add_order_stats(quantity);
+        let limit = self.limits[limit_idx].as_mut().unwrap();
+        limit.add_order_stats(displayed);
+        limit.update_hidden_stats(0, undisplayed);
+        if hidden {
+            limit.hidden_order_count += 1;
+        } else if limit.displayed_order_count() == 1 {
+            self.level_displayed(limit_idx);
+        }
 
         Ok(())
     }
 
     /// Remove an order from a limit's linked list
-    fn remove_order_from_limit(&mut self, order_idx: usize, limit_idx: usize) -> Result<()> {
+    pub(crate) fn remove_order_from_limit(&mut self, order_idx: usize, limit_idx: usize) -> Result<()> {
+        let order_id = self.orders[order_idx].as_ref().unwrap().id;
+
+        self.unlink_order_from_limit(order_idx, limit_idx)?;
+
+        // Remove order from tracking
+        self.order_id_to_index.remove(&order_id);
+        self.free_order_index(order_idx);
+
+        Ok(())
+    }
+
+    /// Detach an order from a limit's linked list without freeing it
+    ///
+    /// The limit is removed if this was its last order.
+    fn unlink_order_from_limit(&mut self, order_idx: usize, limit_idx: usize) -> Result<()> {
+        self.detach_order(order_idx, limit_idx);
+
+        let order = self.orders[order_idx].as_mut().unwrap();
+        order.parent_limit_index = None;
+        let (displayed, undisplayed, hidden) = (order.displayed_quantity(), order.undisplayed_quantity(), order.hidden);
+
+        // Update limit statistics
+        let limit = self.limits[limit_idx].as_mut().unwrap();
+        limit.remove_order_stats(displayed);
+        limit.update_hidden_stats(undisplayed, 0);
+        if hidden {
+            limit.hidden_order_count -= 1;
+        } else if limit.displayed_order_count() == 0 {
+            self.level_undisplayed(limit_idx);
+        }
+
+        // If limit is now empty, remove it
+        if self.limits[limit_idx].as_ref().unwrap().is_empty() {
+            self.remove_empty_limit(limit_idx)?;
+        }
+
+        Ok(())
+    }
+
+    /// Move an order to the back of its priority group in its limit's queue
+    fn requeue_order(&mut self, order_idx: usize, limit_idx: usize) {
+        self.detach_order(order_idx, limit_idx);
+        self.link_order(order_idx, limit_idx);
+    }
+
+    /// Insert an order into a limit's queue (FIFO)
+    ///
+    /// Displayed orders go behind the last displayed order, ahead of every
+    /// hidden one; hidden orders go to the tail.
+    fn link_order(&mut self, order_idx: usize, limit_idx: usize) {
+        let hidden = self.orders[order_idx].as_ref().unwrap().hidden;
+        let limit = self.limits[limit_idx].as_mut().unwrap();
+
+        // The order goes right before `next_idx` (None for the tail)
+        let next_idx = if hidden { None } else { limit.first_hidden_order_index };
+        let prev_idx = match next_idx {
+            Some(next) => self.orders[next].as_ref().unwrap().prev_order_index,
+            None => limit.tail_order_index,
+        };
+        if hidden && limit.first_hidden_order_index.is_none() {
+            limit.first_hidden_order_index = Some(order_idx);
+        }
+
+        match prev_idx {
+            Some(prev) => self.orders[prev].as_mut().unwrap().next_order_index = Some(order_idx),
+            None => limit.head_order_index = Some(order_idx),
+        }
+        match next_idx {
+            Some(next) => self.orders[next].as_mut().unwrap().prev_order_index = Some(order_idx),
+            None => limit.tail_order_index = Some(order_idx),
+        }
+
+        let order = self.orders[order_idx].as_mut().unwrap();
+        order.prev_order_index = prev_idx;
+        order.next_order_index = next_idx;
+    }
+
+    /// Take an order out of a limit's queue, leaving the statistics alone
+    fn detach_order(&mut self, order_idx: usize, limit_idx: usize) {
         // Extract order data before borrowing mutably
-        let (prev_idx, next_idx, order_id, quantity) = {
-            let order = self.orders[order_idx].as_ref().unwrap();
-            (order.prev_order_index, order.next_order_index, order.id, order.quantity)
+        let (prev_idx, next_idx) = {
+            let order = self.orders[order_idx].as_mut().unwrap();
+            let links = (order.prev_order_index, order.next_order_index);
+            order.prev_order_index = None;
+            order.next_order_index = None;
+            links
         };
 
+        let limit = self.limits[limit_idx].as_mut().unwrap();
+        if limit.first_hidden_order_index == Some(order_idx) {
+            // Hidden orders form the end of the queue, so the next one is hidden too
+            limit.first_hidden_order_index = next_idx;
+        }
+
         // Update linked list pointers
         if let Some(prev) = prev_idx {
             self.orders[prev].as_mut().unwrap().next_order_index = next_idx;
         } else {
             // This was the head
-            self.limits[limit_idx].as_mut().unwrap().head_order_index = next_idx;
+            limit.head_order_index = next_idx;
         }
 
         if let Some(next) = next_idx {
             self.orders[next].as_mut().unwrap().prev_order_index = prev_idx;
         } else {
             // This was the tail
-            self.limits[limit_idx].as_mut().unwrap().tail_order_index = prev_idx;
-        }
-
-        // Update limit statistics
-        self.limits[limit_idx].as_mut().unwrap().remove_order_stats(quantity);
-
-        // Remove order from tracking
-        self.order_id_to_index.remove(&order_id);
-        self.free_order_index(order_idx);
-
-        // If limit is now empty, remove it
-        if self.limits[limit_idx].as_ref().unwrap().is_empty() {
-            self.remove_empty_limit(limit_idx)?;
+            limit.tail_order_index = prev_idx;
         }
-
-        Ok(())
     }
 
     /// Remove an empty limit level
@@ -447,26 +1114,26 @@ impl OrderBook {
         let limit = self.limits[limit_idx].as_ref().unwrap();
         let price = limit.price;
         let side = limit.side;
+        let lower = limit.lower_limit_index;
+        let higher = limit.higher_limit_index;
 
-        // Remove from price mapping
-        self.price_to_limit_index.remove(&price);
-
-        // Remove from appropriate tree based on side
-        match side {
-            Side::Buy => {
-                self.buy_tree_root = self.remove_from_tree(self.buy_tree_root, limit_idx);
-            }
-            Side::Sell => {
-                self.sell_tree_root = self.remove_from_tree(self.sell_tree_root, limit_idx);
-            }
+        // Unlink from the neighbouring levels
+        if let Some(lower) = lower {
+            self.limits[lower].as_mut().unwrap().higher_limit_index = higher;
+        }
+        if let Some(higher) = higher {
+            self.limits[higher].as_mut().unwrap().lower_limit_index = lower;
         }
 
-        // Update best prices if this was the best
+        // Remove from price index
+        self.price_index_mut(side).remove(price);
+
+        // If this was the best level, the next one is its neighbour
         if Some(limit_idx) == self.best_bid_index {
-            self.best_bid_index = self.find_new_best_bid();
+            self.best_bid_index = lower;
         }
         if Some(limit_idx) == self.best_ask_index {
-            self.best_ask_index = self.find_new_best_ask();
+            self.best_ask_index = higher;
         }
 
         // Free the limit
@@ -475,6 +1142,61 @@ impl OrderBook {
         Ok(())
     }
 
+    /// Record a level gaining its first displayed order
+    ///
+    /// Levels holding only hidden orders are not published, so this is where
+    /// a level appears in the displayed book and `LevelCreated` is recorded.
+    fn level_displayed(&mut self, limit_idx: usize) {
+        let limit = self.limits[limit_idx].as_ref().unwrap();
+        let (side, price) = (limit.side, limit.price);
+        let best = match side {
+            Side::Buy => self.best_displayed_bid_index,
+            Side::Sell => self.best_displayed_ask_index,
+        };
+        let improves = best.is_none_or(|best| {
+            let best_price = self.limits[best].as_ref().unwrap().price;
+            match side {
+                Side::Buy => price > best_price,
+                Side::Sell => price < best_price,
+            }
+        });
+        if improves {
+            match side {
+                Side::Buy => self.best_displayed_bid_index = Some(limit_idx),
+                Side::Sell => self.best_displayed_ask_index = Some(limit_idx),
+            }
+        }
+        self.emit(BookEvent::LevelCreated { side, price, timestamp: self.current_time });
+    }
+
+    /// Record a level losing its last displayed order, recording `LevelRemoved`
+    ///
+    /// Must run while the level is still linked to its neighbours. If it was
+    /// the best displayed level, the next one is found by skipping any levels
+    /// behind it that hold only hidden orders.
+    fn level_undisplayed(&mut self, limit_idx: usize) {
+        let limit = self.limits[limit_idx].as_ref().unwrap();
+        let (side, price) = (limit.side, limit.price);
+        let best = match side {
+            Side::Buy => self.best_displayed_bid_index,
+            Side::Sell => self.best_displayed_ask_index,
+        };
+        if best == Some(limit_idx) {
+            let mut next = self.next_worse_level(limit_idx);
+            while let Some(idx) = next {
+                if self.limits[idx].as_ref().unwrap().displayed_order_count() > 0 {
+                    break;
+                }
+                next = self.next_worse_level(idx);
+            }
+            match side {
+                Side::Buy => self.best_displayed_bid_index = next,
+                Side::Sell => self.best_displayed_ask_index = next,
+            }
+        }
+        self.emit(BookEvent::LevelRemoved { side, price, timestamp: self.current_time });
+    }
+
     /// Update best bid/ask prices
     fn update_best_prices(&mut self, limit_idx: usize, side: Side) {
         let price = self.limits[limit_idx].as_ref().unwrap().price;
@@ -495,203 +1217,117 @@ impl OrderBook {
         }
     }
 
-    /// Find new best bid after removal
-    fn find_new_best_bid(&self) -> Option<usize> {
-        self.find_max_in_tree_with_orders(self.buy_tree_root)
-    }
-
-    /// Find new best ask after removal
-    fn find_new_best_ask(&self) -> Option<usize> {
-        self.find_min_in_tree_with_orders(self.sell_tree_root)
-    }
-
-    /// Find maximum node in tree that has orders
-    fn find_max_in_tree_with_orders(&self, root: Option<usize>) -> Option<usize> {
-        self.find_max_with_orders_recursive(root)
-    }
-
-    /// Find minimum node in tree that has orders
-    fn find_min_in_tree_with_orders(&self, root: Option<usize>) -> Option<usize> {
-        self.find_min_with_orders_recursive(root)
-    }
-
-    /// Recursively find the maximum node with orders
-    fn find_max_with_orders_recursive(&self, root: Option<usize>) -> Option<usize> {
-        match root {
-            None => None,
-            Some(idx) => {
-                // Check if this limit has orders
-                if let Some(limit) = self.limits[idx].as_ref() {
-                    if !limit.is_empty() {
-                        // This node has orders, check if there's a larger one in right subtree
-                        if let Some(right_result) = self.find_max_with_orders_recursive(limit.avl_node.right_child) {
-                            Some(right_result)
-                        } else {
-                            Some(idx)
-                        }
-                    } else {
-                        // This node is empty, check both subtrees
-                        if let Some(right_result) = self.find_max_with_orders_recursive(limit.avl_node.right_child) {
-                            Some(right_result)
-                        } else {
-                            self.find_max_with_orders_recursive(limit.avl_node.left_child)
-                        }
-                    }
-                } else {
-                    None
-                }
-            }
+    /// Find the next level away from the top of the book on the same side
+    ///
+    /// For bids this is the next lower price, for asks the next higher price.
+    /// O(1): follows the limit's neighbour link.
+    pub(crate) fn next_worse_level(&self, limit_idx: usize) -> Option<usize> {
+        let limit = self.limits[limit_idx].as_ref().unwrap();
+        match limit.side {
+            Side::Buy => limit.lower_limit_index,
+            Side::Sell => limit.higher_limit_index,
         }
     }
+}
 
-    /// Recursively find the minimum node with orders
-    fn find_min_with_orders_recursive(&self, root: Option<usize>) -> Option<usize> {
-        match root {
-            None => None,
-            Some(idx) => {
-                // Check if this limit has orders
-                if let Some(limit) = self.limits[idx].as_ref() {
-                    if !limit.is_empty() {
-                        // This node has orders, check if there's a smaller one in left subtree
-                        if let Some(left_result) = self.find_min_with_orders_recursive(limit.avl_node.left_child) {
-                            Some(left_result)
-                        } else {
-                            Some(idx)
-                        }
-                    } else {
-                        // This node is empty, check both subtrees
-                        if let Some(left_result) = self.find_min_with_orders_recursive(limit.avl_node.left_child) {
-                            Some(left_result)
-                        } else {
-                            self.find_min_with_orders_recursive(limit.avl_node.right_child)
-                        }
-                    }
-                } else {
-                    None
-                }
-            }
-        }
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    /// Insert a limit into the tree (simplified BST, no balancing)
-    fn insert_into_tree(&mut self, root: Option<usize>, limit_idx: usize) -> usize {
-        match root {
-            None => limit_idx,
-            Some(root_idx) => {
-                let limit_price = self.limits[limit_idx].as_ref().unwrap().price;
-                let root_price = self.limits[root_idx].as_ref().unwrap().price;
-
-                if limit_price < root_price {
-                    let new_left = self.insert_into_tree(
-                        self.limits[root_idx].as_ref().unwrap().avl_node.left_child,
-                        limit_idx
-                    );
-                    self.limits[root_idx].as_mut().unwrap().avl_node.left_child = Some(new_left);
-                    self.limits[new_left].as_mut().unwrap().avl_node.parent = Some(root_idx);
-                } else if limit_price > root_price {
-                    let new_right = self.insert_into_tree(
-                        self.limits[root_idx].as_ref().unwrap().avl_node.right_child,
-                        limit_idx
-                    );
-                    self.limits[root_idx].as_mut().unwrap().avl_node.right_child = Some(new_right);
-                    self.limits[new_right].as_mut().unwrap().avl_node.parent = Some(root_idx);
-                }
-
-                // Return root without balancing
-                root_idx
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::level_index::BTreeIndex;
+    use crate::ladder::LadderIndex;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    /// Check that the neighbour links of one side agree with its index
+    fn check_links<I: PriceLevelIndex>(book: &OrderBook<I>, side: Side) {
+        let levels = book.price_index(side);
+        let mut expected = match side {
+            Side::Buy => levels.highest(),
+            Side::Sell => levels.lowest(),
+        };
+        let best = match side {
+            Side::Buy => book.best_bid_index,
+            Side::Sell => book.best_ask_index,
+        };
+        assert_eq!(best, expected);
+        let best_displayed = match side {
+            Side::Buy => book.best_displayed_bid_index,
+            Side::Sell => book.best_displayed_ask_index,
+        };
+        let first_displayed = std::iter::successors(expected, |&idx| book.next_worse_level(idx))
+            .find(|&idx| book.limits[idx].as_ref().unwrap().displayed_order_count() > 0);
+        assert_eq!(best_displayed, first_displayed);
+
+        let mut previous: Option<usize> = None;
+        let mut count = 0;
+        while let Some(idx) = expected {
+            let limit = book.limits[idx].as_ref().unwrap();
+            let (better, worse) = match side {
+                Side::Buy => (limit.higher_limit_index, limit.lower_limit_index),
+                Side::Sell => (limit.lower_limit_index, limit.higher_limit_index),
+            };
+            assert_eq!(better, previous, "back link of {}", limit.price);
+            assert!(!limit.is_empty());
+
+            expected = match side {
+                Side::Buy => levels.next_lower(limit.price),
+                Side::Sell => levels.next_higher(limit.price),
+            };
+            assert_eq!(worse, expected, "forward link of {}", limit.price);
+            previous = Some(idx);
+            count += 1;
         }
+        assert_eq!(count, levels.len());
     }
 
-    /// Remove a limit from the tree (simplified)
-    fn remove_from_tree(&mut self, root: Option<usize>, limit_idx: usize) -> Option<usize> {
-        match root {
-            None => None,
-            Some(root_idx) => {
-                if root_idx == limit_idx {
-                    // This is the node to remove
-                    let node = &self.limits[root_idx].as_ref().unwrap().avl_node;
-
-                    match (node.left_child, node.right_child) {
-                        (None, None) => None,
-                        (Some(left), None) => {
-                            self.limits[left].as_mut().unwrap().avl_node.parent = node.parent;
-                            Some(left)
-                        }
-                        (None, Some(right)) => {
-                            self.limits[right].as_mut().unwrap().avl_node.parent = node.parent;
-                            Some(right)
-                        }
-                        (Some(_), Some(right)) => {
-                            // Find successor (minimum in right subtree)
-                            let successor_idx = self.find_min_in_subtree(right);
-
-                            // Replace current node's data with successor's data
-                            let successor_price = self.limits[successor_idx].as_ref().unwrap().price;
-                            self.limits[root_idx].as_mut().unwrap().price = successor_price;
-
-                            // Remove successor from right subtree
-                            let new_right = self.remove_from_tree(Some(right), successor_idx);
-                            self.limits[root_idx].as_mut().unwrap().avl_node.right_child = new_right;
-
-                            Some(root_idx)
-                        }
-                    }
-                } else {
-                    let limit_price = self.limits[limit_idx].as_ref().unwrap().price;
-                    let root_price = self.limits[root_idx].as_ref().unwrap().price;
-
-                    if limit_price < root_price {
-                        let new_left = self.remove_from_tree(
-                            self.limits[root_idx].as_ref().unwrap().avl_node.left_child,
-                            limit_idx
-                        );
-                        self.limits[root_idx].as_mut().unwrap().avl_node.left_child = new_left;
-                    } else {
-                        let new_right = self.remove_from_tree(
-                            self.limits[root_idx].as_ref().unwrap().avl_node.right_child,
-                            limit_idx
-                        );
-                        self.limits[root_idx].as_mut().unwrap().avl_node.right_child = new_right;
-                    }
-
-                    Some(root_idx)
+    fn links_stay_consistent<I: PriceLevelIndex>() {
+        let mut book = OrderBook::<I>::with_backend(InstrumentConfig::default().with_price_band(1, 400));
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut live = Vec::new();
+
+        for id in 0..3000u64 {
+            if !live.is_empty() && rng.gen_bool(0.4) {
+                let victim = live.swap_remove(rng.gen_range(0..live.len()));
+                book.remove_order(victim).unwrap();
+            } else {
+                let side = if rng.gen_bool(0.5) { Side::Buy } else { Side::Sell };
+                let (low, high) = match side {
+                    Side::Buy => (1, 220),
+                    Side::Sell => (180, 400),
+                };
+                let order = Order::new(id, side, rng.gen_range(1..20), rng.gen_range(low..=high), id, 1)
+                    .with_hidden(rng.gen_bool(0.3));
+                book.submit(order).unwrap();
+                live.retain(|&id| book.contains_order(id));
+                if book.contains_order(id) {
+                    live.push(id);
                 }
             }
-        }
-    }
 
-    /// Find minimum node in subtree (for tree operations)
-    fn find_min_in_subtree(&self, mut index: usize) -> usize {
-        while let Some(left) = self.limits[index].as_ref().unwrap().avl_node.left_child {
-            index = left;
+            if id % 50 == 0 {
+                check_links(&book, Side::Buy);
+                check_links(&book, Side::Sell);
+            }
         }
-        index
-    }
-}
-
-impl Default for OrderBook {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl AvlTree<Limit> for OrderBook {
-    fn get_price(&self, index: usize) -> Price {
-        self.limits[index].as_ref()
-            .expect(&format!("Limit at index {} should exist", index))
-            .price
-    }
 
-    fn get_node(&self, index: usize) -> &AvlNode {
-        &self.limits[index].as_ref()
-            .expect(&format!("Limit at index {} should exist", index))
-            .avl_node
+        // Sweep the whole ask side so the best level empties repeatedly
+        let sweep = Order::new(10_000, Side::Buy, u32::MAX as u64, 400, 0, 1);
+        book.submit(sweep).unwrap();
+        assert_eq!(book.best_ask(), None);
+        check_links(&book, Side::Buy);
+        check_links(&book, Side::Sell);
     }
 
-    fn get_node_mut(&mut self, index: usize) -> &mut AvlNode {
-        &mut self.limits[index].as_mut()
-            .expect(&format!("Limit at index {} should exist", index))
-            .avl_node
+    #[test]
+    fn test_level_links_stay_consistent() {
+        links_stay_consistent::<AvlIndex>();
+        links_stay_consistent::<BTreeIndex>();
+        links_stay_consistent::<LadderIndex>();
     }
 }
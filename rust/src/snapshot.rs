@@ -0,0 +1,305 @@
+//! Point-in-time snapshots of the logical book content
+//!
+//! The arena layout of `OrderBook` (free lists, price-level indexes, cached
+//! best-level indices) is an implementation detail that depends on the history of the book,
+//! so it is never serialized. A `BookSnapshot` only records the resting orders,
+//! per side, best price first and FIFO within each price. `OrderBook::restore`
+//! replays them into a fresh book, which rebuilds the arenas and indexes and
+//! reproduces every level's queue exactly. The book's `InstrumentConfig` and
+//! `TradingPhase` are captured too, so the restored book enforces the same
+//! rules, along with the running auction's reference price, parked stop orders
+//! and the last trade price they trigger on.
+//!
+//! The generator of randomized iceberg peak sizes is not captured: a restored
+//! book starts from the default seed, so icebergs with a peak variance may
+//! replenish with different sizes than in the original. Call
+//! `OrderBook::set_peak_seed` on the restored book to choose the seed.
+
+use crate::instrument::InstrumentConfig;
+use crate::level_index::PriceLevelIndex;
+use crate::order::Order;
+use crate::orderbook::OrderBook;
+use crate::types::{Price, Side, Timestamp, TradingPhase};
+use crate::{OrderBookError, Result};
+
+#[cfg(feature = "serde_support")]
+use serde::{Deserialize, Serialize};
+
+/// Logical content of an orderbook
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct BookSnapshot {
+    /// Book time when the snapshot was taken
+    pub timestamp: Timestamp,
+    /// Instrument rules of the book
+    pub config: InstrumentConfig,
+    /// Trading phase of the book
+    pub phase: TradingPhase,
+    /// Tie-break price of the running call auction
+    pub auction_reference_price: Option<Price>,
+    /// Resting buy orders, best price first and FIFO within each price
+    pub bids: Vec<Order>,
+    /// Resting sell orders, best price first and FIFO within each price
+    pub asks: Vec<Order>,
+    /// Parked stop orders, buys then sells, each in trigger order
+    pub stops: Vec<Order>,
+    /// Price of the most recent trade
+    pub last_trade_price: Option<Price>,
+}
+
+impl BookSnapshot {
+    /// Total number of orders in the snapshot, parked stops included
+    pub fn total_orders(&self) -> usize {
+        self.bids.len() + self.asks.len() + self.stops.len()
+    }
+}
+
+impl<I: PriceLevelIndex> OrderBook<I> {
+    /// Capture every resting order in price-time priority
+    pub fn snapshot(&self) -> BookSnapshot {
+        let side_orders = |side: Side| -> Vec<Order> {
+            let levels = match side {
+                Side::Buy => self.bids_with_hidden(),
+                Side::Sell => self.asks_with_hidden(),
+            };
+            levels
+                .flat_map(|limit| self.orders_at_level(side, limit.price))
+                .map(detached)
+                .collect()
+        };
+
+        BookSnapshot {
+            timestamp: self.current_time,
+            config: self.config,
+            phase: self.phase,
+            auction_reference_price: self.auction_reference_price,
+            bids: side_orders(Side::Buy),
+            asks: side_orders(Side::Sell),
+            stops: self.stop_orders().cloned().collect(),
+            last_trade_price: self.last_trade_price,
+        }
+    }
+
+    /// Rebuild a book from a snapshot
+    ///
+    /// Orders are re-inserted in snapshot order, so each level's FIFO queue is
+    /// restored exactly, and must pass the checks `add_order` and `submit`
+    /// apply under the snapshot's config. Entry and event times are kept as
+    /// recorded. Event
+    /// recording is disabled on the returned book, which uses price-level
+    /// index `I`. The phase and auction reference price are restored as is,
+    /// without a `PhaseChanged` event; iceberg peak sizes are drawn from the
+    /// default seed.
+    pub fn restore_with_backend(snapshot: &BookSnapshot) -> Result<Self> {
+        let mut book = Self::with_backend_and_capacity(snapshot.config, snapshot.total_orders(), 0);
+        book.set_time(snapshot.timestamp);
+        book.last_trade_price = snapshot.last_trade_price;
+
+        for (side, orders) in [(Side::Buy, &snapshot.bids), (Side::Sell, &snapshot.asks)] {
+            for order in orders {
+                if order.side != side {
+                    return Err(OrderBookError::InvalidSnapshot(format!(
+                        "order {} is listed on the {} side",
+                        order.id, side
+                    )));
+                }
+                book.validate_resting_order(order)?;
+                if order.quantity == 0 {
+                    return Err(OrderBookError::InvalidQuantity(order.quantity));
+                }
+                if book.contains_order(order.id) {
+                    return Err(OrderBookError::OrderAlreadyExists(order.id));
+                }
+
+                book.add_order_to_book(detached(order))?;
+            }
+        }
+
+        for order in &snapshot.stops {
+            let Some(stop_price) = order.stop_price else {
+                return Err(OrderBookError::InvalidSnapshot(format!(
+                    "order {} is listed as a stop without a stop price",
+                    order.id
+                )));
+            };
+            book.config.validate_price(stop_price)?;
+            if order.order_type.has_limit_price() {
+                book.config.validate_price(order.price)?;
+            }
+            if order.order_type.can_rest() {
+                book.check_price_fits(order.side, order.price)?;
+            }
+            book.validate_order_quantity(order)?;
+            if book.contains_order(order.id) || book.stops.contains(order.id) {
+                return Err(OrderBookError::OrderAlreadyExists(order.id));
+            }
+
+            book.stops.insert(detached(order));
+        }
+
+        // Set last, as orders are checked like `add_order` in continuous trading
+        book.phase = snapshot.phase;
+        book.auction_reference_price = snapshot.auction_reference_price;
+        Ok(book)
+    }
+}
+
+impl OrderBook {
+    /// Rebuild a book from a snapshot
+    ///
+    /// See `restore_with_backend`; the returned book uses the default index.
+    pub fn restore(snapshot: &BookSnapshot) -> Result<OrderBook> {
+        Self::restore_with_backend(snapshot)
+    }
+}
+
+/// Copy of an order with its arena links cleared
+fn detached(order: &Order) -> Order {
+    let mut order = order.clone();
+    order.next_order_index = None;
+    order.prev_order_index = None;
+    order.parent_limit_index = None;
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OrderId, OrderType};
+
+    fn sample_book() -> OrderBook {
+        let mut book = OrderBook::new();
+        book.set_time(1000);
+        book.add_order(Order::new(1, Side::Buy, 10, 4990, 1000, 1)).unwrap();
+        book.add_order(Order::new(2, Side::Buy, 20, 5000, 1001, 1)).unwrap();
+        book.add_order(Order::new(3, Side::Buy, 30, 4990, 1002, 2)).unwrap();
+        book.add_order(Order::new(4, Side::Sell, 40, 5010, 1003, 2)).unwrap();
+        book.add_order(Order::new(5, Side::Sell, 50, 5020, 1004, 2)).unwrap();
+        book.add_order(Order::new(6, Side::Sell, 60, 5010, 1005, 2)).unwrap();
+        book.set_time(1010);
+        // Loses priority behind order 3
+        book.amend_order(1, 4990, 15).unwrap();
+        book.remove_order(5).unwrap();
+        book
+    }
+
+    fn queue(book: &OrderBook, side: Side, price: Price) -> Vec<OrderId> {
+        book.orders_at_level(side, price).map(|o| o.id).collect()
+    }
+
+    #[test]
+    fn test_round_trip_preserves_priority() {
+        let book = sample_book();
+        let snapshot = book.snapshot();
+        let bid_ids: Vec<_> = snapshot.bids.iter().map(|o| o.id).collect();
+        assert_eq!(bid_ids, vec![2, 3, 1]);
+
+        let restored = OrderBook::restore(&snapshot).unwrap();
+        assert_eq!(restored.current_time(), 1010);
+        assert_eq!(restored.total_orders(), 5);
+        assert_eq!(restored.total_levels(), book.total_levels());
+        assert_eq!(restored.best_bid(), Some((5000, 20)));
+        assert_eq!(restored.best_ask(), Some((5010, 100)));
+        assert_eq!(queue(&restored, Side::Buy, 4990), vec![3, 1]);
+        assert_eq!(queue(&restored, Side::Sell, 5010), vec![4, 6]);
+        assert_eq!(restored.get_order(1).unwrap().entry_time, 1010);
+        assert_eq!(restored.queue_position(1), Some(30));
+        assert_eq!(restored.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_restored_book_matches_in_same_order() {
+        let mut original = sample_book();
+        let mut restored = OrderBook::restore(&original.snapshot()).unwrap();
+
+        let aggressor = Order::new(10, Side::Sell, 45, 4990, 1010, 1);
+        let expected = original.submit(aggressor.clone()).unwrap();
+        let actual = restored.submit(aggressor).unwrap();
+        assert_eq!(actual, expected);
+        assert_eq!(restored.snapshot(), original.snapshot());
+    }
+
+    #[test]
+    fn test_round_trip_keeps_stops() {
+        let mut original = sample_book();
+        original.submit(Order::new(10, Side::Sell, 5, 5000, 1010, 1)).unwrap();
+        original.submit(Order::market(11, Side::Sell, 20, 1010, 1).with_stop(4990)).unwrap();
+        original.submit(Order::market(12, Side::Buy, 10, 1010, 1).with_stop(5010)).unwrap();
+        let snapshot = original.snapshot();
+        assert_eq!(snapshot.stops.iter().map(|o| o.id).collect::<Vec<_>>(), vec![12, 11]);
+        assert_eq!(snapshot.last_trade_price, Some(5000));
+
+        let mut restored = OrderBook::restore(&snapshot).unwrap();
+        assert_eq!(restored.total_stop_orders(), 2);
+        let aggressor = Order::new(13, Side::Sell, 20, 4990, 1010, 1);
+        assert_eq!(restored.submit(aggressor.clone()).unwrap(), original.submit(aggressor).unwrap());
+        assert_eq!(restored.snapshot(), original.snapshot());
+    }
+
+    #[test]
+    fn test_round_trip_keeps_auction_reference_price() {
+        let mut book = OrderBook::new();
+        book.start_call_auction(Some(5003)).unwrap();
+        book.submit(Order::new(1, Side::Buy, 10, 5005, 1000, 1)).unwrap();
+        book.submit(Order::new(2, Side::Sell, 10, 5000, 1000, 2)).unwrap();
+        let snapshot = book.snapshot();
+        assert_eq!(snapshot.auction_reference_price, Some(5003));
+
+        // The reference price breaks the tie between 5000 and 5005
+        let mut restored = OrderBook::restore(&snapshot).unwrap();
+        assert_eq!(restored.indicative_price(), Some(5005));
+        assert_eq!(restored.uncross().unwrap().price, book.uncross().unwrap().price);
+        assert_eq!(restored.snapshot(), book.snapshot());
+    }
+
+    #[test]
+    fn test_restore_rejects_invalid_snapshots() {
+        let mut snapshot = sample_book().snapshot();
+        snapshot.asks.push(snapshot.bids[0].clone());
+        assert!(matches!(
+            OrderBook::restore(&snapshot),
+            Err(OrderBookError::InvalidSnapshot(_))
+        ));
+
+        let mut snapshot = sample_book().snapshot();
+        let duplicate = snapshot.bids[0].clone();
+        snapshot.bids.push(duplicate);
+        assert_eq!(OrderBook::restore(&snapshot).unwrap_err(), OrderBookError::OrderAlreadyExists(2));
+
+        let mut snapshot = sample_book().snapshot();
+        snapshot.bids[0].order_type = OrderType::ImmediateOrCancel;
+        assert_eq!(
+            OrderBook::restore(&snapshot).unwrap_err(),
+            OrderBookError::InvalidOrderType(OrderType::ImmediateOrCancel)
+        );
+
+        let mut snapshot = sample_book().snapshot();
+        snapshot.bids[0].stop_price = Some(5100);
+        assert_eq!(OrderBook::restore(&snapshot).unwrap_err(), OrderBookError::InvalidOrderType(OrderType::Limit));
+
+        let mut snapshot = sample_book().snapshot();
+        snapshot.config = InstrumentConfig::new(1, 10);
+        assert_eq!(OrderBook::restore(&snapshot).unwrap_err(), OrderBookError::InvalidLot(15));
+
+        let mut snapshot = sample_book().snapshot();
+        snapshot.asks[0] = snapshot.asks[0].clone().with_peak(10).with_hidden(true);
+        assert_eq!(OrderBook::restore(&snapshot).unwrap_err(), OrderBookError::InvalidOrderType(OrderType::Limit));
+
+        let mut snapshot = sample_book().snapshot();
+        snapshot.stops.push(Order::new(20, Side::Buy, 10, 5003, 1010, 1).with_stop(5010));
+        snapshot.config = InstrumentConfig::new(5, 1);
+        assert_eq!(OrderBook::restore(&snapshot).unwrap_err(), OrderBookError::InvalidTick(5003));
+    }
+
+    #[cfg(feature = "serde_support")]
+    #[test]
+    fn test_serde_round_trip() {
+        let book = sample_book();
+        let json = serde_json::to_string(&book.snapshot()).unwrap();
+        let snapshot: BookSnapshot = serde_json::from_str(&json).unwrap();
+
+        let restored = OrderBook::restore(&snapshot).unwrap();
+        assert_eq!(restored.snapshot(), book.snapshot());
+        assert_eq!(queue(&restored, Side::Buy, 4990), vec![3, 1]);
+    }
+}
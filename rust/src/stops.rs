@@ -0,0 +1,203 @@
+//! Stop and stop-limit orders
+//!
+//! An order carrying a `stop_price` is not added to the book on `submit`.
+//! It is parked in the book's trigger structure, keyed by stop price per side,
+//! until the last trade price reaches it: at or above the stop price for a
+//! buy, at or below it for a sell. The order is then submitted as its order
+//! type, so a stop market order becomes a market order and a stop-limit order
+//! a limit order. A stop that is already triggered when it arrives is
+//! submitted straight away.
+//!
+//! Triggered orders trade and may move the last price far enough to trigger
+//! further stops. Such cascades run to completion within the `submit` (or
+//! `uncross`) that started them, one stop at a time, re-checking the last
+//! price after each. The next stop to fire is always:
+//!
+//! 1. a buy stop before a sell stop,
+//! 2. the stop price closest to the market, the lowest for buys and the
+//!    highest for sells,
+//! 3. the stop parked first at that price.
+//!
+//! Stops only fire while the book matches orders; outside continuous trading
+//! they stay parked. `remove_order` cancels parked stops like resting orders.
+//!
+//! A stop is validated when parked and again when triggered, since the
+//! instrument config may change in between. One that fails the second check
+//! is dropped with a `StopRejected` event instead of being submitted.
+
+use crate::events::BookEvent;
+use crate::level_index::PriceLevelIndex;
+use crate::matching::ExecutionReport;
+use crate::order::Order;
+use crate::orderbook::OrderBook;
+use crate::types::{OrderId, Price, Side};
+use crate::Result;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+
+/// Parked stop orders of one book, in trigger order per side
+#[derive(Debug, Default)]
+pub(crate) struct StopBook {
+    /// Buy stops by (stop price, arrival), lowest stop first
+    buys: BTreeMap<(Price, u64), Order>,
+    /// Sell stops by (stop price, arrival), highest stop first
+    sells: BTreeMap<(Reverse<Price>, u64), Order>,
+    /// Side, stop price and arrival of each parked stop
+    keys: HashMap<OrderId, (Side, Price, u64)>,
+    /// Arrival counter keeping stops at the same price in FIFO order
+    next_sequence: u64,
+}
+
+impl StopBook {
+    /// Number of parked stops
+    pub(crate) fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Check if a stop with this ID is parked
+    pub(crate) fn contains(&self, order_id: OrderId) -> bool {
+        self.keys.contains_key(&order_id)
+    }
+
+    /// Get a parked stop by ID
+    pub(crate) fn get(&self, order_id: OrderId) -> Option<&Order> {
+        let &(side, stop_price, sequence) = self.keys.get(&order_id)?;
+        match side {
+            Side::Buy => self.buys.get(&(stop_price, sequence)),
+            Side::Sell => self.sells.get(&(Reverse(stop_price), sequence)),
+        }
+    }
+
+    /// Park a stop order behind the stops already parked at its price
+    pub(crate) fn insert(&mut self, order: Order) {
+        let stop_price = order.stop_price.expect("only stop orders are parked");
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.keys.insert(order.id, (order.side, stop_price, sequence));
+        match order.side {
+            Side::Buy => self.buys.insert((stop_price, sequence), order),
+            Side::Sell => self.sells.insert((Reverse(stop_price), sequence), order),
+        };
+    }
+
+    /// Take a parked stop out by ID
+    pub(crate) fn remove(&mut self, order_id: OrderId) -> Option<Order> {
+        let (side, stop_price, sequence) = self.keys.remove(&order_id)?;
+        match side {
+            Side::Buy => self.buys.remove(&(stop_price, sequence)),
+            Side::Sell => self.sells.remove(&(Reverse(stop_price), sequence)),
+        }
+    }
+
+    /// Take out the next stop triggered by a trade at `last_price`, if any
+    pub(crate) fn pop_triggered(&mut self, last_price: Price) -> Option<Order> {
+        let order_id = self.buys.values().next()
+            .filter(|order| order.stop_triggered_by(last_price))
+            .or_else(|| self.sells.values().next().filter(|order| order.stop_triggered_by(last_price)))?
+            .id;
+        self.remove(order_id)
+    }
+
+    /// Parked stops, buys then sells, each in trigger order
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Order> {
+        self.buys.values().chain(self.sells.values())
+    }
+}
+
+impl<I: PriceLevelIndex> OrderBook<I> {
+    /// Get a parked stop order by ID
+    pub fn get_stop_order(&self, order_id: OrderId) -> Option<&Order> {
+        self.stops.get(order_id)
+    }
+
+    /// Iterate parked stop orders, buys then sells, each in trigger order
+    pub fn stop_orders(&self) -> impl Iterator<Item = &Order> {
+        self.stops.iter()
+    }
+
+    /// Get total number of parked stop orders
+    pub fn total_stop_orders(&self) -> usize {
+        self.stops.len()
+    }
+
+    /// Submit every stop triggered by the last trade price, cascading
+    ///
+    /// Returns one report per triggered order, in firing order. A triggered
+    /// order that no longer passes validation is dropped with `StopRejected`
+    /// and its report carries the error.
+    pub(crate) fn fire_stops(&mut self) -> Vec<ExecutionReport> {
+        let mut reports = Vec::new();
+        if !self.phase.matches_orders() {
+            return reports;
+        }
+        while let Some(order) = self.last_trade_price.and_then(|last| self.stops.pop_triggered(last)) {
+            let order = self.trigger_stop(order);
+            let (order_id, side, quantity) = (order.id, order.side, order.total_quantity());
+            let result = self.validate_triggered_order(&order).and_then(|()| self.execute(order));
+            let report = result.unwrap_or_else(|error| {
+                self.emit(BookEvent::StopRejected { order_id, timestamp: self.current_time });
+                ExecutionReport::rejected(order_id, side, quantity, error)
+            });
+            reports.push(report);
+        }
+        reports
+    }
+
+    /// Check a triggered order against the rules in force now
+    ///
+    /// The instrument config, or the range of prices a bounded level index
+    /// can hold, may have changed since the stop was parked.
+    fn validate_triggered_order(&self, order: &Order) -> Result<()> {
+        if order.order_type.has_limit_price() {
+            self.config.validate_price(order.price)?;
+        }
+        if order.order_type.can_rest() {
+            self.check_price_fits(order.side, order.price)?;
+        }
+        self.validate_order_quantity(order)
+    }
+
+    /// Turn a stop into the order it submits, recording `StopTriggered`
+    pub(crate) fn trigger_stop(&mut self, mut order: Order) -> Order {
+        self.emit(BookEvent::StopTriggered {
+            order_id: order.id,
+            side: order.side,
+            stop_price: order.stop_price.take().expect("only stop orders are triggered"),
+            timestamp: self.current_time,
+        });
+        order.event_time = self.current_time;
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(id: OrderId, side: Side, stop_price: Price) -> Order {
+        Order::market(id, side, 10, id, 1).with_stop(stop_price)
+    }
+
+    #[test]
+    fn test_trigger_order() {
+        let mut stops = StopBook::default();
+        stops.insert(stop(1, Side::Buy, 105));
+        stops.insert(stop(2, Side::Buy, 103));
+        stops.insert(stop(3, Side::Buy, 103));
+        stops.insert(stop(4, Side::Sell, 95));
+        stops.insert(stop(5, Side::Sell, 97));
+        assert_eq!(stops.iter().map(|o| o.id).collect::<Vec<_>>(), vec![2, 3, 1, 5, 4]);
+
+        assert_eq!(stops.pop_triggered(100), None);
+        assert_eq!(stops.pop_triggered(104).map(|o| o.id), Some(2));
+        assert_eq!(stops.pop_triggered(104).map(|o| o.id), Some(3));
+        assert_eq!(stops.pop_triggered(104), None);
+        assert_eq!(stops.pop_triggered(90).map(|o| o.id), Some(5));
+
+        assert_eq!(stops.remove(4).map(|o| o.id), Some(4));
+        assert_eq!(stops.remove(4), None);
+        assert!(stops.contains(1));
+        assert_eq!(stops.get(1).and_then(|o| o.stop_price), Some(105));
+        assert_eq!(stops.len(), 1);
+    }
+}
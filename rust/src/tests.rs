@@ -1,6 +1,6 @@
 //! Simple tests for the new orderbook design
 
-use crate::{OrderBook, Order, Side, OrderBookError};
+use crate::{AuctionResult, BookEvent, InstrumentConfig, LadderIndex, LevelDelta, OrderBook, Order, OrderType, PreventedTrade, SelfTradePrevention, Side, Trade, TradingPhase, OrderBookError};
 
 #[test]
 fn test_empty_orderbook() {
@@ -602,3 +602,1362 @@ fn test_comprehensive_orderbook_operations() {
     assert_eq!(book.total_orders(), 3);
     assert_eq!(book.total_levels(), 3);
 }
+
+// ============================================================================
+// Matching engine tests
+// ============================================================================
+
+#[test]
+fn test_submit_without_cross_rests() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+
+    book.submit(Order::new(1, Side::Sell, 100, 5000, 1000, 1)).unwrap();
+    let report = book.submit(Order::new(2, Side::Buy, 50, 4990, 1001, 1)).unwrap();
+
+    assert!(report.trades.is_empty());
+    assert_eq!(report.filled_quantity, 0);
+    assert_eq!(report.remaining_quantity, 50);
+    assert!(report.rested);
+    assert_eq!(book.best_bid(), Some((4990, 50)));
+    assert_eq!(book.best_ask(), Some((5000, 100)));
+}
+
+#[test]
+fn test_submit_fills_in_price_time_priority() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+
+    book.add_order(Order::new(1, Side::Sell, 100, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 50, 5000, 1001, 1)).unwrap();
+    book.add_order(Order::new(3, Side::Sell, 200, 5010, 1002, 1)).unwrap();
+    book.add_order(Order::new(4, Side::Sell, 75, 5060, 1003, 1)).unwrap();
+
+    book.set_time(2000);
+    let report = book.submit(Order::new(10, Side::Buy, 200, 5050, 2000, 1)).unwrap();
+
+    let fills: Vec<_> = report.trades.iter()
+        .map(|t| (t.passive_order_id, t.price, t.quantity))
+        .collect();
+    assert_eq!(fills, vec![(1, 5000, 100), (2, 5000, 50), (3, 5010, 50)]);
+    assert!(report.trades.iter().all(|t| t.aggressor_order_id == 10
+        && t.aggressor_side == Side::Buy
+        && t.timestamp == 2000));
+    assert_eq!(report.filled_quantity, 200);
+    assert!(report.is_filled());
+    assert!(!report.rested);
+
+    assert!(!book.contains_order(1));
+    assert!(!book.contains_order(2));
+    assert!(!book.contains_order(10));
+    assert_eq!(book.get_order(3).unwrap().quantity, 150);
+    assert_eq!(book.volume_at_price(5000), None);
+    assert_eq!(book.best_ask(), Some((5010, 150)));
+    assert_eq!(book.best_bid(), None);
+}
+
+#[test]
+fn test_submit_rests_remainder_after_sweep() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+
+    book.add_order(Order::new(1, Side::Buy, 30, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Buy, 40, 4990, 1001, 1)).unwrap();
+    book.add_order(Order::new(3, Side::Buy, 50, 4980, 1002, 1)).unwrap();
+
+    let report = book.submit(Order::new(4, Side::Sell, 100, 4990, 1003, 1)).unwrap();
+
+    assert_eq!(report.filled_quantity, 70);
+    assert_eq!(report.remaining_quantity, 30);
+    assert!(report.rested);
+    assert_eq!(report.trades.len(), 2);
+    assert_eq!(book.best_bid(), Some((4980, 50)));
+    assert_eq!(book.best_ask(), Some((4990, 30)));
+    assert_eq!(book.total_orders(), 2);
+    assert_eq!(book.total_levels(), 2);
+}
+
+#[test]
+fn test_submit_sweeps_levels_with_two_children() {
+    // Build an ask tree whose root has both children so that removing
+    // levels exercises successor splicing in the tree.
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+
+    let prices = [5050, 5020, 5080, 5010, 5030, 5070, 5090];
+    for (i, &price) in prices.iter().enumerate() {
+        book.add_order(Order::new(i as u64 + 1, Side::Sell, 10, price, 1000, 1)).unwrap();
+    }
+
+    let report = book.submit(Order::new(100, Side::Buy, 45, 5080, 1001, 1)).unwrap();
+    let traded: Vec<_> = report.trades.iter().map(|t| t.price).collect();
+    assert_eq!(traded, vec![5010, 5020, 5030, 5050, 5070]);
+    assert_eq!(book.best_ask(), Some((5070, 5)));
+
+    book.remove_order(6).unwrap();
+    assert_eq!(book.best_ask(), Some((5080, 10)));
+    book.remove_order(3).unwrap();
+    assert_eq!(book.best_ask(), Some((5090, 10)));
+    assert_eq!(book.total_levels(), 1);
+}
+
+#[test]
+fn test_submit_validation() {
+    let mut book = OrderBook::new();
+    book.add_order(Order::new(1, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+
+    assert_eq!(
+        book.submit(Order::new(2, Side::Buy, 10, 0, 1000, 1)),
+        Err(OrderBookError::InvalidPrice(0))
+    );
+    assert_eq!(
+        book.submit(Order::new(2, Side::Buy, 0, 5000, 1000, 1)),
+        Err(OrderBookError::InvalidQuantity(0))
+    );
+    assert_eq!(
+        book.submit(Order::new(1, Side::Buy, 10, 5000, 1000, 1)),
+        Err(OrderBookError::OrderAlreadyExists(1))
+    );
+    assert_eq!(book.volume_at_price(5000), Some(10));
+}
+
+#[test]
+fn test_add_order_rejects_non_limit_types() {
+    let mut book = OrderBook::new();
+
+    let market = Order::market(1, Side::Buy, 10, 1000, 1);
+    assert_eq!(book.add_order(market), Err(OrderBookError::InvalidOrderType(OrderType::Market)));
+
+    let ioc = Order::new(2, Side::Buy, 10, 5000, 1000, 1).with_order_type(OrderType::ImmediateOrCancel);
+    assert_eq!(book.add_order(ioc), Err(OrderBookError::InvalidOrderType(OrderType::ImmediateOrCancel)));
+    assert_eq!(book.total_orders(), 0);
+}
+
+#[test]
+fn test_submit_market_order() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+
+    book.add_order(Order::new(1, Side::Sell, 100, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 100, 6000, 1001, 1)).unwrap();
+
+    let report = book.submit(Order::market(3, Side::Buy, 250, 1002, 1)).unwrap();
+    assert_eq!(report.filled_quantity, 200);
+    assert_eq!(report.remaining_quantity, 50);
+    assert_eq!(report.cancelled_quantity(), 50);
+    assert!(!report.rested);
+    assert_eq!(report.trades.iter().map(|t| t.price).collect::<Vec<_>>(), vec![5000, 6000]);
+
+    assert!(!book.contains_order(3));
+    assert_eq!(book.total_orders(), 0);
+    assert_eq!(book.best_bid(), None);
+}
+
+#[test]
+fn test_submit_ioc_order() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+
+    book.add_order(Order::new(1, Side::Buy, 60, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Buy, 60, 4990, 1001, 1)).unwrap();
+
+    let ioc = Order::new(3, Side::Sell, 100, 5000, 1002, 1)
+        .with_order_type(OrderType::ImmediateOrCancel);
+    let report = book.submit(ioc).unwrap();
+
+    assert_eq!(report.filled_quantity, 60);
+    assert_eq!(report.cancelled_quantity(), 40);
+    assert!(!report.rested);
+    assert!(!book.contains_order(3));
+    assert_eq!(book.best_bid(), Some((4990, 60)));
+    assert_eq!(book.best_ask(), None);
+
+    // An IOC that doesn't cross at all is cancelled outright
+    let ioc = Order::new(4, Side::Sell, 10, 5100, 1003, 1)
+        .with_order_type(OrderType::ImmediateOrCancel);
+    let report = book.submit(ioc).unwrap();
+    assert!(report.trades.is_empty());
+    assert_eq!(report.cancelled_quantity(), 10);
+    assert_eq!(book.total_orders(), 1);
+}
+
+#[test]
+fn test_submit_fok_order() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+
+    book.add_order(Order::new(1, Side::Sell, 50, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 50, 5010, 1001, 1)).unwrap();
+    book.add_order(Order::new(3, Side::Sell, 50, 5020, 1002, 1)).unwrap();
+
+    // Only 100 available at or below 5010: killed without touching the book
+    let fok = Order::new(4, Side::Buy, 120, 5010, 1003, 1)
+        .with_order_type(OrderType::FillOrKill);
+    let report = book.submit(fok).unwrap();
+    assert!(report.trades.is_empty());
+    assert_eq!(report.cancelled_quantity(), 120);
+    assert_eq!(book.best_ask(), Some((5000, 50)));
+    assert_eq!(book.total_orders(), 3);
+
+    // Enough liquidity at or below 5020: filled completely
+    let fok = Order::new(5, Side::Buy, 120, 5020, 1004, 1)
+        .with_order_type(OrderType::FillOrKill);
+    let report = book.submit(fok).unwrap();
+    assert!(report.is_filled());
+    assert_eq!(report.trades.len(), 3);
+    assert_eq!(book.best_ask(), Some((5020, 30)));
+}
+
+#[test]
+fn test_same_price_on_both_sides() {
+    // A locked book: bid and ask at the same price must live in separate levels
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+
+    book.add_order(Order::new(1, Side::Sell, 100, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Buy, 40, 5000, 1001, 1)).unwrap();
+    book.add_order(Order::new(3, Side::Buy, 10, 5000, 1002, 1)).unwrap();
+
+    assert_eq!(book.total_levels(), 2);
+    assert_eq!(book.best_bid(), Some((5000, 50)));
+    assert_eq!(book.best_ask(), Some((5000, 100)));
+    assert_eq!(book.volume_at_price_for_side(Side::Buy, 5000), Some(50));
+    assert_eq!(book.volume_at_price_for_side(Side::Sell, 5000), Some(100));
+    assert_eq!(book.orders_at_price_for_side(Side::Buy, 5000), Some(2));
+    assert_eq!(book.orders_at_price_for_side(Side::Sell, 5000), Some(1));
+    assert_eq!(book.volume_at_price(5000), Some(150));
+    assert_eq!(book.orders_at_price(5000), Some(3));
+
+    let (bids, asks) = book.get_levels(None);
+    assert_eq!(bids, vec![(5000, 50)]);
+    assert_eq!(asks, vec![(5000, 100)]);
+
+    // Emptying the bid level leaves the ask level untouched
+    book.remove_order(2).unwrap();
+    book.remove_order(3).unwrap();
+    assert_eq!(book.total_levels(), 1);
+    assert_eq!(book.best_bid(), None);
+    assert_eq!(book.volume_at_price_for_side(Side::Buy, 5000), None);
+    assert_eq!(book.best_ask(), Some((5000, 100)));
+    assert_eq!(book.volume_at_price(5000), Some(100));
+}
+
+#[test]
+fn test_crossed_book_replay() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+
+    book.add_order(Order::new(1, Side::Sell, 10, 4990, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 20, 5000, 1001, 1)).unwrap();
+    book.add_order(Order::new(3, Side::Buy, 30, 5000, 1002, 1)).unwrap();
+    book.add_order(Order::new(4, Side::Buy, 40, 5010, 1003, 1)).unwrap();
+
+    assert_eq!(book.best_bid(), Some((5010, 40)));
+    assert_eq!(book.best_ask(), Some((4990, 10)));
+    assert_eq!(book.get_order(3).unwrap().side, Side::Buy);
+    assert_eq!(book.volume_at_price_for_side(Side::Buy, 5000), Some(30));
+    assert_eq!(book.volume_at_price_for_side(Side::Sell, 5000), Some(20));
+    assert_eq!(book.volume_at_price_for_side(Side::Sell, 5010), None);
+}
+
+// ============================================================================
+// Amend tests
+// ============================================================================
+
+/// Sweep the whole ask side and return the passive order IDs in fill order
+fn ask_fill_order(book: &mut OrderBook, aggressor_id: u64) -> Vec<u64> {
+    let report = book.submit(Order::market(aggressor_id, Side::Buy, u64::MAX / 2, 0, 1)).unwrap();
+    report.trades.iter().map(|t| t.passive_order_id).collect()
+}
+
+#[test]
+fn test_amend_decrease_keeps_priority() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+    book.add_order(Order::new(1, Side::Sell, 100, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 100, 5000, 1001, 1)).unwrap();
+
+    book.set_time(2000);
+    let result = book.amend_order(1, 5000, 60).unwrap();
+    assert!(!result.priority_lost);
+    assert_eq!((result.old_quantity, result.new_quantity), (100, 60));
+    assert_eq!(book.volume_at_price(5000), Some(160));
+    assert_eq!(book.get_order(1).unwrap().entry_time, 1000);
+
+    assert_eq!(ask_fill_order(&mut book, 99), vec![1, 2]);
+}
+
+#[test]
+fn test_amend_increase_loses_priority() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+    book.add_order(Order::new(1, Side::Sell, 100, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 100, 5000, 1001, 1)).unwrap();
+    book.add_order(Order::new(3, Side::Sell, 100, 5000, 1002, 1)).unwrap();
+
+    book.set_time(2000);
+    let result = book.amend_order(1, 5000, 150).unwrap();
+    assert!(result.priority_lost);
+    assert_eq!(book.volume_at_price(5000), Some(350));
+    assert_eq!(book.orders_at_price(5000), Some(3));
+    assert_eq!(book.get_order(1).unwrap().entry_time, 2000);
+
+    assert_eq!(ask_fill_order(&mut book, 99), vec![2, 3, 1]);
+}
+
+#[test]
+fn test_amend_price_change_moves_level() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+    book.add_order(Order::new(1, Side::Sell, 100, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 50, 5010, 1001, 1)).unwrap();
+
+    // Moving the only order at 5000 removes that level
+    let result = book.amend_order(1, 5010, 80).unwrap();
+    assert!(result.priority_lost);
+    assert_eq!((result.old_price, result.new_price), (5000, 5010));
+    assert_eq!(book.volume_at_price(5000), None);
+    assert_eq!(book.total_levels(), 1);
+    assert_eq!(book.best_ask(), Some((5010, 130)));
+    assert_eq!(book.get_order(1).unwrap().price, 5010);
+
+    // Price improvement becomes the new best
+    book.amend_order(2, 4990, 50).unwrap();
+    assert_eq!(book.best_ask(), Some((4990, 50)));
+    assert_eq!(book.total_levels(), 2);
+
+    assert_eq!(ask_fill_order(&mut book, 99), vec![2, 1]);
+}
+
+#[test]
+fn test_amend_sole_order_increase_stays_on_level() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+    book.add_order(Order::new(1, Side::Buy, 100, 5000, 1000, 1)).unwrap();
+
+    book.set_time(1500);
+    let result = book.amend_order(1, 5000, 200).unwrap();
+    assert!(result.priority_lost);
+    assert_eq!(book.best_bid(), Some((5000, 200)));
+    assert_eq!(book.orders_at_price(5000), Some(1));
+    assert_eq!(book.get_order(1).unwrap().entry_time, 1500);
+}
+
+#[test]
+fn test_amend_errors() {
+    let mut book = OrderBook::new();
+    book.add_order(Order::new(1, Side::Buy, 100, 5000, 1000, 1)).unwrap();
+
+    assert_eq!(book.amend_order(2, 5000, 10), Err(OrderBookError::OrderNotFound(2)));
+    assert_eq!(book.amend_order(1, 0, 10), Err(OrderBookError::InvalidPrice(0)));
+    assert_eq!(book.amend_order(1, 5000, 0), Err(OrderBookError::InvalidQuantity(0)));
+    assert_eq!(book.volume_at_price(5000), Some(100));
+}
+
+// ============================================================================
+// Queue inspection tests
+// ============================================================================
+
+#[test]
+fn test_orders_at_level_fifo() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+    book.add_order(Order::new(1, Side::Buy, 10, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Buy, 20, 5000, 1001, 1)).unwrap();
+    book.add_order(Order::new(3, Side::Buy, 30, 5000, 1002, 1)).unwrap();
+    book.add_order(Order::new(4, Side::Sell, 40, 5000, 1003, 1)).unwrap();
+
+    let ids: Vec<_> = book.orders_at_level(Side::Buy, 5000).map(|o| o.id).collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+    let ids: Vec<_> = book.orders_at_level(Side::Sell, 5000).map(|o| o.id).collect();
+    assert_eq!(ids, vec![4]);
+    assert_eq!(book.orders_at_level(Side::Buy, 4000).count(), 0);
+
+    book.remove_order(2).unwrap();
+    book.amend_order(1, 5000, 15).unwrap();
+    let queue: Vec<_> = book.orders_at_level(Side::Buy, 5000)
+        .map(|o| (o.id, o.quantity))
+        .collect();
+    assert_eq!(queue, vec![(3, 30), (1, 15)]);
+}
+
+#[test]
+fn test_queue_position() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+    book.add_order(Order::new(1, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 20, 5000, 1001, 1)).unwrap();
+    book.add_order(Order::new(3, Side::Sell, 30, 5000, 1002, 1)).unwrap();
+
+    assert_eq!(book.queue_position(1), Some(0));
+    assert_eq!(book.queue_position(2), Some(10));
+    assert_eq!(book.queue_position(3), Some(30));
+    assert_eq!(book.queue_position(99), None);
+
+    // A partial fill at the head shrinks the queue ahead of everyone else
+    book.submit(Order::new(10, Side::Buy, 4, 5000, 1003, 1)).unwrap();
+    assert_eq!(book.queue_position(2), Some(6));
+    assert_eq!(book.queue_position(3), Some(26));
+
+    book.update_order(2, 5).unwrap();
+    assert_eq!(book.queue_position(3), Some(11));
+}
+
+#[test]
+fn test_level_iterators() {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+
+    // Insert in an order that produces an unsorted tree shape
+    let bid_prices = [4950, 4900, 4990, 4920, 4970, 4910, 4980];
+    let ask_prices = [5050, 5100, 5010, 5080, 5030, 5090, 5020];
+    for (i, &price) in bid_prices.iter().enumerate() {
+        book.add_order(Order::new(i as u64 + 1, Side::Buy, 10, price, 1000, 1)).unwrap();
+    }
+    for (i, &price) in ask_prices.iter().enumerate() {
+        book.add_order(Order::new(i as u64 + 100, Side::Sell, 10, price, 1000, 1)).unwrap();
+    }
+
+    let bids: Vec<_> = book.bids().map(|l| l.price).collect();
+    assert_eq!(bids, vec![4990, 4980, 4970, 4950, 4920, 4910, 4900]);
+    let asks: Vec<_> = book.asks().map(|l| l.price).collect();
+    assert_eq!(asks, vec![5010, 5020, 5030, 5050, 5080, 5090, 5100]);
+
+    let top: Vec<_> = book.asks().take(2).map(|l| (l.price, l.size, l.order_count)).collect();
+    assert_eq!(top, vec![(5010, 10, 1), (5020, 10, 1)]);
+
+    // Removing interior levels keeps the walk consistent
+    book.remove_order(1).unwrap();
+    book.remove_order(103).unwrap();
+    let bids: Vec<_> = book.bids().map(|l| l.price).collect();
+    assert_eq!(bids, vec![4990, 4980, 4970, 4920, 4910, 4900]);
+    let asks: Vec<_> = book.asks().map(|l| l.price).collect();
+    assert_eq!(asks, vec![5010, 5020, 5030, 5050, 5090, 5100]);
+
+    assert_eq!(OrderBook::new().bids().count(), 0);
+}
+
+// ============================================================================
+// Event stream tests
+// ============================================================================
+
+#[test]
+fn test_events_disabled_by_default() {
+    let mut book = OrderBook::new();
+    book.add_order(Order::new(1, Side::Buy, 10, 5000, 1000, 1)).unwrap();
+    assert!(!book.events_enabled());
+    assert!(book.drain_events().is_empty());
+}
+
+#[test]
+fn test_events_add_modify_cancel() {
+    let mut book = OrderBook::new();
+    book.set_events_enabled(true);
+    book.set_time(1000);
+
+    book.add_order(Order::new(1, Side::Buy, 10, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Buy, 20, 4990, 1000, 1)).unwrap();
+    assert_eq!(book.drain_events(), vec![
+        BookEvent::LevelCreated { side: Side::Buy, price: 5000, timestamp: 1000 },
+        BookEvent::OrderAdded {
+            order_id: 1,
+            quantity: 10,
+            entry_time: 1000,
+            exchange_id: 1,
+            level: LevelDelta::new(Side::Buy, 5000, 0, 10),
+            timestamp: 1000,
+        },
+        BookEvent::BboChanged { best_bid: Some((5000, 10)), best_ask: None, timestamp: 1000 },
+        BookEvent::LevelCreated { side: Side::Buy, price: 4990, timestamp: 1000 },
+        BookEvent::OrderAdded {
+            order_id: 2,
+            quantity: 20,
+            entry_time: 1000,
+            exchange_id: 1,
+            level: LevelDelta::new(Side::Buy, 4990, 0, 20),
+            timestamp: 1000,
+        },
+    ]);
+
+    book.set_time(1001);
+    book.update_order(2, 15).unwrap();
+    let level = LevelDelta::new(Side::Buy, 4990, 20, 15);
+    assert_eq!(book.drain_events(), vec![BookEvent::OrderModified {
+        order_id: 2,
+        old_quantity: 20,
+        new_quantity: 15,
+        priority_lost: false,
+        old_level: level,
+        new_level: level,
+        timestamp: 1001,
+    }]);
+
+    book.set_time(1002);
+    book.remove_order(1).unwrap();
+    assert_eq!(book.drain_events(), vec![
+        BookEvent::OrderCancelled {
+            order_id: 1,
+            quantity: 10,
+            level: LevelDelta::new(Side::Buy, 5000, 10, 0),
+            timestamp: 1002,
+        },
+        BookEvent::LevelRemoved { side: Side::Buy, price: 5000, timestamp: 1002 },
+        BookEvent::BboChanged { best_bid: Some((4990, 15)), best_ask: None, timestamp: 1002 },
+    ]);
+}
+
+#[test]
+fn test_events_amend_across_levels() {
+    let mut book = OrderBook::new();
+    book.add_order(Order::new(1, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 20, 5000, 1000, 1)).unwrap();
+    book.set_events_enabled(true);
+    book.set_time(2000);
+
+    book.amend_order(1, 4990, 5).unwrap();
+    assert_eq!(book.drain_events(), vec![
+        BookEvent::LevelCreated { side: Side::Sell, price: 4990, timestamp: 2000 },
+        BookEvent::OrderModified {
+            order_id: 1,
+            old_quantity: 10,
+            new_quantity: 5,
+            priority_lost: true,
+            old_level: LevelDelta::new(Side::Sell, 5000, 30, 20),
+            new_level: LevelDelta::new(Side::Sell, 4990, 0, 5),
+            timestamp: 2000,
+        },
+        BookEvent::BboChanged { best_bid: None, best_ask: Some((4990, 5)), timestamp: 2000 },
+    ]);
+}
+
+#[test]
+fn test_events_for_matching() {
+    let mut book = OrderBook::new();
+    book.add_order(Order::new(1, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 20, 5010, 1000, 1)).unwrap();
+    book.set_events_enabled(true);
+    book.set_time(3000);
+
+    book.submit(Order::new(3, Side::Buy, 15, 5010, 3000, 1)).unwrap();
+    assert_eq!(book.drain_events(), vec![
+        BookEvent::OrderFilled {
+            order_id: 1,
+            aggressor_order_id: 3,
+            quantity: 10,
+            remaining_quantity: 0,
+            level: LevelDelta::new(Side::Sell, 5000, 10, 0),
+            timestamp: 3000,
+        },
+        BookEvent::LevelRemoved { side: Side::Sell, price: 5000, timestamp: 3000 },
+        BookEvent::OrderFilled {
+            order_id: 2,
+            aggressor_order_id: 3,
+            quantity: 5,
+            remaining_quantity: 15,
+            level: LevelDelta::new(Side::Sell, 5010, 20, 15),
+            timestamp: 3000,
+        },
+        BookEvent::BboChanged { best_bid: None, best_ask: Some((5010, 15)), timestamp: 3000 },
+    ]);
+
+    // Disabling discards anything pending
+    book.remove_order(2).unwrap();
+    book.set_events_enabled(false);
+    book.set_events_enabled(true);
+    assert!(book.drain_events().is_empty());
+}
+
+// ============================================================================
+// Instrument rule tests
+// ============================================================================
+
+#[test]
+fn test_instrument_rules_reject_malformed_orders() {
+    let config = InstrumentConfig::new(5, 100)
+        .with_quantity_limits(100, 1_000)
+        .with_price_band(4_000, 6_000);
+    let mut book = OrderBook::with_config(config);
+
+    assert_eq!(
+        book.add_order(Order::new(1, Side::Buy, 100, 5002, 1000, 1)),
+        Err(OrderBookError::InvalidTick(5002))
+    );
+    assert_eq!(
+        book.add_order(Order::new(1, Side::Buy, 150, 5000, 1000, 1)),
+        Err(OrderBookError::InvalidLot(150))
+    );
+    assert_eq!(
+        book.add_order(Order::new(1, Side::Buy, 2_000, 5000, 1000, 1)),
+        Err(OrderBookError::QuantityOutOfRange(2_000))
+    );
+    assert_eq!(
+        book.add_order(Order::new(1, Side::Buy, 100, 7000, 1000, 1)),
+        Err(OrderBookError::PriceOutOfBand(7000))
+    );
+    assert_eq!(
+        book.submit(Order::new(1, Side::Sell, 100, 5003, 1000, 1)),
+        Err(OrderBookError::InvalidTick(5003))
+    );
+    assert_eq!(book.total_orders(), 0);
+
+    book.add_order(Order::new(1, Side::Buy, 200, 5000, 1000, 1)).unwrap();
+    assert_eq!(book.update_order(1, 250), Err(OrderBookError::InvalidLot(250)));
+    assert_eq!(book.amend_order(1, 5001, 200), Err(OrderBookError::InvalidTick(5001)));
+    assert_eq!(book.get_order(1).unwrap().quantity, 200);
+
+    // Market orders have no price to check, but their quantity still is
+    assert_eq!(
+        book.submit(Order::market(2, Side::Sell, 1_100, 1000, 1)),
+        Err(OrderBookError::QuantityOutOfRange(1_100))
+    );
+    assert!(book.submit(Order::market(2, Side::Sell, 100, 1000, 1)).unwrap().is_filled());
+}
+
+#[test]
+fn test_decimal_prices_and_quantities() {
+    let config = InstrumentConfig::new(5, 1).with_scales(2, 1);
+    let mut book = OrderBook::with_config(config);
+
+    let order = Order::from_decimal(1, Side::Buy, "10.5", "50.25", 1000, 1, &config).unwrap();
+    assert_eq!((order.quantity, order.price), (105, 5025));
+    assert_eq!(order.display_with(&config).to_string(), "Order[1]: Buy 10.5 @ 50.25");
+    book.add_order(order).unwrap();
+    book.add_order(Order::from_decimal(2, Side::Sell, "3", "50.3", 1000, 1, &config).unwrap()).unwrap();
+
+    let (price, quantity) = book.best_bid_decimal().unwrap();
+    assert_eq!((price.to_string(), quantity.to_string()), ("50.25".to_string(), "10.5".to_string()));
+    let (price, quantity) = book.best_ask_decimal().unwrap();
+    assert_eq!(format!("{} x {}", quantity, price), "3.0 x 50.30");
+
+    assert_eq!(
+        Order::from_decimal(3, Side::Buy, "1", "50.251", 1000, 1, &config),
+        Err(OrderBookError::InvalidDecimal("50.251".to_string()))
+    );
+    // Representable, but off the 0.05 tick
+    let off_tick = Order::from_decimal(3, Side::Buy, "1", "50.26", 1000, 1, &config).unwrap();
+    assert_eq!(book.add_order(off_tick), Err(OrderBookError::InvalidTick(5026)));
+}
+
+// ============================================================================
+// Signed price tests
+// ============================================================================
+
+#[test]
+fn test_negative_prices_are_rejected_by_default() {
+    let mut book = OrderBook::new();
+    assert_eq!(
+        book.add_order(Order::new(1, Side::Buy, 10, -100, 1000, 1)),
+        Err(OrderBookError::InvalidPrice(-100))
+    );
+    assert_eq!(
+        book.submit(Order::new(1, Side::Buy, 10, 0, 1000, 1)),
+        Err(OrderBookError::InvalidPrice(0))
+    );
+}
+
+#[test]
+fn test_calendar_spread_book_across_zero() {
+    let mut book = OrderBook::with_config(InstrumentConfig::default().with_negative_prices());
+
+    for (id, side, price) in [
+        (1, Side::Buy, -30),
+        (2, Side::Buy, -5),
+        (3, Side::Buy, -120),
+        (4, Side::Sell, 15),
+        (5, Side::Sell, 0),
+        (6, Side::Sell, -2),
+    ] {
+        book.add_order(Order::new(id, side, 10, price, 1000, 1)).unwrap();
+    }
+
+    let bids: Vec<_> = book.bids().map(|l| l.price).collect();
+    let asks: Vec<_> = book.asks().map(|l| l.price).collect();
+    assert_eq!(bids, vec![-5, -30, -120]);
+    assert_eq!(asks, vec![-2, 0, 15]);
+    assert_eq!(book.spread(), Some(3));
+    // (-5 + -2) / 2 = -3.5 rounds down
+    assert_eq!(book.mid_price(), Some(-4));
+    assert_eq!(book.get_levels(Some(1)).0[0].0, -5);
+    assert_eq!(book.bids().nth(1).unwrap().total_value(), -300);
+
+    // A buy at 0 sweeps the asks at -2 and 0 at their own prices
+    let report = book.submit(Order::new(7, Side::Buy, 25, 0, 1000, 1)).unwrap();
+    let fills: Vec<_> = report.trades.iter().map(|t| (t.price, t.quantity)).collect();
+    assert_eq!(fills, vec![(-2, 10), (0, 10)]);
+    assert_eq!(book.best_bid(), Some((0, 5)));
+    assert_eq!(book.best_ask(), Some((15, 10)));
+}
+
+#[test]
+fn test_crossed_book_has_negative_spread() {
+    let mut book = OrderBook::new();
+    book.add_order(Order::new(1, Side::Buy, 10, 5010, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+    assert_eq!(book.spread(), Some(-10));
+    assert_eq!(book.mid_price(), Some(5005));
+}
+
+// ============================================================================
+// Self-trade prevention tests
+// ============================================================================
+
+/// Book with asks of 10 @ 5000 (owner 7), 10 @ 5000 (owner 8) and 10 @ 5001 (owner 7)
+fn self_trade_book() -> OrderBook {
+    let mut book = OrderBook::new();
+    book.add_order(Order::new(1, Side::Sell, 10, 5000, 1000, 1).with_owner(7)).unwrap();
+    book.add_order(Order::new(2, Side::Sell, 10, 5000, 1001, 1).with_owner(8)).unwrap();
+    book.add_order(Order::new(3, Side::Sell, 10, 5001, 1002, 1).with_owner(7)).unwrap();
+    book
+}
+
+fn own_buy(quantity: u64, mode: SelfTradePrevention) -> Order {
+    Order::new(10, Side::Buy, quantity, 5001, 2000, 1)
+        .with_owner(7)
+        .with_self_trade_prevention(mode)
+}
+
+#[test]
+fn test_self_trade_cancel_newest() {
+    let mut book = self_trade_book();
+    let report = book.submit(own_buy(25, SelfTradePrevention::CancelNewest)).unwrap();
+
+    assert!(report.trades.is_empty());
+    assert_eq!(
+        report.prevented,
+        vec![PreventedTrade {
+            resting_order_id: 1,
+            price: 5000,
+            action: SelfTradePrevention::CancelNewest,
+            incoming_cancelled: 25,
+            resting_cancelled: 0,
+        }]
+    );
+    assert_eq!(report.remaining_quantity, 0);
+    assert!(!report.rested);
+    assert!(!report.is_filled());
+    assert_eq!(report.cancelled_quantity(), 25);
+    assert_eq!(book.total_orders(), 3);
+    assert!(!book.contains_order(10));
+}
+
+#[test]
+fn test_self_trade_cancel_oldest() {
+    let mut book = self_trade_book();
+    let report = book.submit(own_buy(25, SelfTradePrevention::CancelOldest)).unwrap();
+
+    // Order 1 is cancelled, order 2 trades, order 3 is cancelled and the rest rests
+    let fills: Vec<_> = report.trades.iter().map(|t| (t.passive_order_id, t.quantity)).collect();
+    assert_eq!(fills, vec![(2, 10)]);
+    let cancelled: Vec<_> = report.prevented.iter().map(|p| (p.resting_order_id, p.resting_cancelled)).collect();
+    assert_eq!(cancelled, vec![(1, 10), (3, 10)]);
+    assert_eq!(report.self_trade_cancelled(), 0);
+    assert_eq!(report.remaining_quantity, 15);
+    assert!(report.rested);
+    assert_eq!(book.best_ask(), None);
+    assert_eq!(book.best_bid(), Some((5001, 15)));
+}
+
+#[test]
+fn test_self_trade_cancel_both() {
+    let mut book = self_trade_book();
+    let report = book.submit(own_buy(25, SelfTradePrevention::CancelBoth)).unwrap();
+
+    assert!(report.trades.is_empty());
+    assert_eq!(report.prevented.len(), 1);
+    assert_eq!(report.prevented[0].incoming_cancelled, 25);
+    assert_eq!(report.prevented[0].resting_cancelled, 10);
+    assert_eq!(report.cancelled_quantity(), 25);
+    assert!(!book.contains_order(1));
+    assert_eq!(book.best_ask(), Some((5000, 10)));
+    assert_eq!(book.best_bid(), None);
+}
+
+#[test]
+fn test_self_trade_decrement_and_cancel() {
+    // Incoming larger than the resting order: resting cancelled, incoming reduced
+    let mut book = self_trade_book();
+    let report = book.submit(own_buy(25, SelfTradePrevention::DecrementAndCancel)).unwrap();
+    let prevented: Vec<_> = report.prevented.iter()
+        .map(|p| (p.resting_order_id, p.incoming_cancelled, p.resting_cancelled))
+        .collect();
+    // 25 - 10 (order 1) = 15, 10 trade with order 2, the last 5 decrement order 3
+    assert_eq!(prevented, vec![(1, 10, 10), (3, 5, 5)]);
+    assert_eq!(report.filled_quantity, 10);
+    assert_eq!(report.remaining_quantity, 0);
+    assert_eq!(report.self_trade_cancelled(), 15);
+    assert!(!report.rested);
+    assert_eq!(book.get_order(3).unwrap().quantity, 5);
+    assert_eq!(book.best_ask(), Some((5001, 5)));
+
+    // Incoming smaller than the resting order: incoming cancelled, resting keeps its place
+    let mut book = self_trade_book();
+    let report = book.submit(own_buy(4, SelfTradePrevention::DecrementAndCancel)).unwrap();
+    assert_eq!(report.prevented[0].incoming_cancelled, 4);
+    assert_eq!(book.orders_at_level(Side::Sell, 5000).map(|o| (o.id, o.quantity)).collect::<Vec<_>>(), vec![(1, 6), (2, 10)]);
+}
+
+#[test]
+fn test_self_trade_only_between_same_owner() {
+    let mut book = self_trade_book();
+    // No owner: trades with everyone
+    let report = book.submit(Order::new(10, Side::Buy, 20, 5000, 2000, 1)).unwrap();
+    assert_eq!(report.filled_quantity, 20);
+    assert!(report.prevented.is_empty());
+
+    // Other owner: trades with owner 7
+    let report = book.submit(Order::new(11, Side::Buy, 10, 5001, 2000, 1).with_owner(9)).unwrap();
+    assert_eq!(report.trades[0].passive_order_id, 3);
+    assert!(report.is_filled());
+}
+
+#[test]
+fn test_self_trade_fill_or_kill_counts_reachable_liquidity() {
+    // Order 1 (own) is first in the queue; under CancelNewest nothing behind it is reachable
+    let mut book = self_trade_book();
+    let fok = own_buy(10, SelfTradePrevention::CancelNewest).with_order_type(OrderType::FillOrKill);
+    let report = book.submit(fok).unwrap();
+    assert!(report.trades.is_empty() && report.prevented.is_empty());
+    assert_eq!(book.total_orders(), 3);
+
+    // Under CancelOldest own orders are skipped, so order 2 covers the quantity
+    let fok = own_buy(10, SelfTradePrevention::CancelOldest).with_order_type(OrderType::FillOrKill);
+    let report = book.submit(fok).unwrap();
+    assert!(report.is_filled());
+    assert_eq!(report.trades[0].passive_order_id, 2);
+    assert!(!book.contains_order(1));
+}
+
+// ============================================================================
+// Call auction tests
+// ============================================================================
+
+#[test]
+fn test_call_auction_accumulates_without_matching() {
+    let mut book = OrderBook::new();
+    book.start_call_auction(None).unwrap();
+    assert_eq!(book.phase(), TradingPhase::AuctionCall);
+
+    let report = book.submit(Order::new(1, Side::Sell, 10, 5000, 1000, 1)).unwrap();
+    assert!(report.rested && report.trades.is_empty());
+    let report = book.submit(Order::new(2, Side::Buy, 10, 5010, 1001, 1)).unwrap();
+    assert!(report.rested && report.trades.is_empty());
+
+    // The book is allowed to cross
+    assert_eq!(book.best_bid(), Some((5010, 10)));
+    assert_eq!(book.best_ask(), Some((5000, 10)));
+
+    let market = Order::new(3, Side::Buy, 10, 0, 1002, 1).with_order_type(OrderType::Market);
+    assert_eq!(book.submit(market), Err(OrderBookError::InvalidOrderType(OrderType::Market)));
+    assert_eq!(book.total_orders(), 2);
+}
+
+#[test]
+fn test_uncross_executes_at_one_price_in_priority() {
+    let mut book = OrderBook::new();
+    book.start_call_auction(Some(5000)).unwrap();
+    book.submit(Order::new(1, Side::Buy, 10, 5002, 1000, 1)).unwrap();
+    book.submit(Order::new(2, Side::Buy, 10, 5001, 1001, 1)).unwrap();
+    book.submit(Order::new(3, Side::Buy, 10, 5002, 1002, 1)).unwrap();
+    book.submit(Order::new(4, Side::Sell, 15, 4999, 1003, 1)).unwrap();
+    book.submit(Order::new(5, Side::Sell, 10, 5001, 1004, 1)).unwrap();
+    book.submit(Order::new(6, Side::Sell, 10, 5003, 1005, 1)).unwrap();
+
+    // 5001 executes 25: buys 30 at or above, sells 25 at or below
+    let uncross = book.indicative_uncross().unwrap();
+    assert_eq!((uncross.price, uncross.volume, uncross.buy_surplus), (5001, 25, 5));
+    assert_eq!(book.indicative_volume(), 25);
+
+    book.set_time(2000);
+    book.set_events_enabled(true);
+    let result = book.uncross().unwrap();
+    assert_eq!(result.price, Some(5001));
+    assert_eq!(result.volume, 25);
+    // Bids 1 and 3 at 5002 go first, then 2; asks 4 at 4999 then 5
+    assert_eq!(
+        result.trades,
+        vec![
+            Trade::new(1, 4, 5001, 10, 2000, Side::Buy),
+            Trade::new(3, 4, 5001, 5, 2000, Side::Buy),
+            Trade::new(3, 5, 5001, 5, 2000, Side::Buy),
+            Trade::new(2, 5, 5001, 5, 2000, Side::Buy),
+        ]
+    );
+
+    // Leftovers rest in an uncrossed book and continuous matching resumes
+    assert_eq!(book.phase(), TradingPhase::Continuous);
+    assert_eq!(book.best_bid(), Some((5001, 5)));
+    assert_eq!(book.best_ask(), Some((5003, 10)));
+    let fills = book.drain_events().iter().filter(|e| matches!(e, BookEvent::OrderFilled { .. })).count();
+    assert_eq!(fills, 8);
+    let report = book.submit(Order::new(7, Side::Sell, 5, 5001, 2001, 1)).unwrap();
+    assert!(report.is_filled());
+    assert_eq!(book.uncross(), Err(OrderBookError::NotAllowedInPhase(TradingPhase::Continuous)));
+}
+
+#[test]
+fn test_uncross_without_cross_leaves_book() {
+    let mut book = OrderBook::new();
+    book.start_call_auction(None).unwrap();
+    book.submit(Order::new(1, Side::Buy, 10, 4999, 1000, 1)).unwrap();
+    assert_eq!(
+        book.uncross(),
+        Ok(AuctionResult { price: None, volume: 0, trades: Vec::new(), triggered: Vec::new() })
+    );
+    assert_eq!(book.best_bid(), Some((4999, 10)));
+}
+
+// ============================================================================
+// Trading phase tests
+// ============================================================================
+
+#[test]
+fn test_phase_rules() {
+    let mut book = OrderBook::new();
+    assert_eq!(book.phase(), TradingPhase::Continuous);
+    book.add_order(Order::new(1, Side::Buy, 10, 5000, 1000, 1)).unwrap();
+    book.add_order(Order::new(2, Side::Buy, 10, 4999, 1000, 1)).unwrap();
+
+    // Halted: cancels only
+    book.set_phase(TradingPhase::Halted).unwrap();
+    let rejected = Err(OrderBookError::NotAllowedInPhase(TradingPhase::Halted));
+    assert_eq!(book.add_order(Order::new(3, Side::Sell, 10, 5001, 1000, 1)), rejected);
+    assert_eq!(book.submit(Order::new(3, Side::Sell, 10, 5000, 1000, 1)).map(|_| ()), rejected);
+    assert_eq!(book.update_order(1, 5), rejected);
+    assert_eq!(book.amend_order(1, 5001, 10).map(|_| ()), rejected);
+    assert_eq!(book.remove_order(2).unwrap().id, 2);
+
+    // Closed: nothing, and only pre-open follows
+    book.set_phase(TradingPhase::Closed).unwrap();
+    let rejected = Err(OrderBookError::NotAllowedInPhase(TradingPhase::Closed));
+    assert_eq!(book.remove_order(1).map(|_| ()), rejected);
+    assert_eq!(book.set_phase(TradingPhase::Continuous), rejected);
+    assert_eq!(book.start_call_auction(None), rejected);
+    book.set_phase(TradingPhase::PreOpen).unwrap();
+
+    // Pre-open: entry and changes are accepted but nothing matches
+    let report = book.submit(Order::new(3, Side::Sell, 10, 4990, 1000, 1)).unwrap();
+    assert!(report.rested && report.trades.is_empty());
+    book.update_order(1, 20).unwrap();
+    assert!(book.is_crossed());
+    assert_eq!(
+        book.set_phase(TradingPhase::Continuous),
+        Err(OrderBookError::NotAllowedInPhase(TradingPhase::PreOpen))
+    );
+    assert_eq!(book.uncross(), Err(OrderBookError::NotAllowedInPhase(TradingPhase::PreOpen)));
+
+    book.start_call_auction(None).unwrap();
+    assert_eq!(book.uncross().unwrap().volume, 10);
+    assert!(!book.is_crossed());
+    assert_eq!(book.best_bid(), Some((5000, 10)));
+}
+
+#[test]
+fn test_phase_changes_are_recorded() {
+    let mut book = OrderBook::new();
+    book.set_events_enabled(true);
+    book.set_time(1000);
+    book.set_phase(TradingPhase::Halted).unwrap();
+    book.set_phase(TradingPhase::Halted).unwrap();
+    book.set_time(2000);
+    book.start_call_auction(Some(5000)).unwrap();
+    book.set_time(3000);
+    book.uncross().unwrap();
+
+    assert_eq!(
+        book.drain_events(),
+        vec![
+            BookEvent::PhaseChanged { from: TradingPhase::Continuous, to: TradingPhase::Halted, timestamp: 1000 },
+            BookEvent::PhaseChanged { from: TradingPhase::Halted, to: TradingPhase::AuctionCall, timestamp: 2000 },
+            BookEvent::PhaseChanged { from: TradingPhase::AuctionCall, to: TradingPhase::Continuous, timestamp: 3000 },
+        ]
+    );
+}
+
+#[test]
+fn test_snapshot_keeps_phase() {
+    let mut book = OrderBook::new();
+    book.add_order(Order::new(1, Side::Buy, 10, 5000, 1000, 1)).unwrap();
+    book.set_phase(TradingPhase::Halted).unwrap();
+
+    let restored = OrderBook::restore(&book.snapshot()).unwrap();
+    assert_eq!(restored.phase(), TradingPhase::Halted);
+    assert_eq!(restored.best_bid(), Some((5000, 10)));
+}
+
+// ============================================================================
+// Iceberg order tests
+// ============================================================================
+
+/// Bid iceberg 1 (100 total, peak 20) ahead of a plain bid 2 of 10, both at 5000
+fn iceberg_book() -> OrderBook {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+    book.add_order(Order::new(1, Side::Buy, 100, 5000, 1000, 1).with_peak(20)).unwrap();
+    book.add_order(Order::new(2, Side::Buy, 10, 5000, 1001, 1)).unwrap();
+    book
+}
+
+fn bid_queue(book: &OrderBook) -> Vec<(u64, u64)> {
+    book.orders_at_level(Side::Buy, 5000).map(|o| (o.id, o.quantity)).collect()
+}
+
+#[test]
+fn test_iceberg_displays_only_peak() {
+    let book = iceberg_book();
+    assert_eq!(book.best_bid(), Some((5000, 30)));
+    assert_eq!(book.volume_at_price(5000), Some(30));
+    assert_eq!(book.get_levels(None).0, vec![(5000, 30)]);
+    assert_eq!(book.bids().next().unwrap().hidden_size, 80);
+
+    let order = book.get_order(1).unwrap();
+    assert_eq!((order.quantity, order.hidden_quantity, order.total_quantity()), (20, 80, 100));
+}
+
+#[test]
+fn test_iceberg_replenishes_to_tail() {
+    let mut book = iceberg_book();
+    book.set_time(2000);
+    book.set_events_enabled(true);
+
+    let report = book.submit(Order::new(3, Side::Sell, 25, 5000, 2000, 1)).unwrap();
+    let fills: Vec<_> = report.trades.iter().map(|t| (t.passive_order_id, t.quantity)).collect();
+    assert_eq!(fills, vec![(1, 20), (2, 5)]);
+
+    // The refilled slice queues behind order 2 with a fresh entry time
+    assert_eq!(bid_queue(&book), vec![(2, 5), (1, 20)]);
+    let order = book.get_order(1).unwrap();
+    assert_eq!((order.hidden_quantity, order.entry_time), (60, 2000));
+    assert_eq!(book.best_bid(), Some((5000, 25)));
+
+    let events = book.drain_events();
+    assert_eq!(
+        events[1],
+        BookEvent::OrderAdded {
+            order_id: 1,
+            quantity: 20,
+            entry_time: 2000,
+            exchange_id: 1,
+            level: LevelDelta::new(Side::Buy, 5000, 10, 30),
+            timestamp: 2000,
+        }
+    );
+}
+
+#[test]
+fn test_iceberg_reserve_is_executable() {
+    let mut book = OrderBook::new();
+    book.add_order(Order::new(1, Side::Buy, 100, 5000, 1000, 1).with_peak(20)).unwrap();
+
+    // FOK sees the reserve, and a lone iceberg keeps refilling until done
+    let fok = Order::new(2, Side::Sell, 70, 5000, 1001, 1).with_order_type(OrderType::FillOrKill);
+    let report = book.submit(fok).unwrap();
+    assert!(report.is_filled());
+    assert_eq!(report.trades.iter().map(|t| t.quantity).collect::<Vec<_>>(), vec![20, 20, 20, 10]);
+    let order = book.get_order(1).unwrap();
+    assert_eq!((order.quantity, order.hidden_quantity), (10, 20));
+
+    let report = book.submit(Order::new(3, Side::Sell, 40, 5000, 1002, 1)).unwrap();
+    assert_eq!(report.filled_quantity, 30);
+    assert!(!book.contains_order(1));
+    assert_eq!(book.best_ask(), Some((5000, 10)));
+    assert_eq!(book.best_bid(), None);
+}
+
+#[test]
+fn test_incoming_iceberg_trades_in_full_then_hides() {
+    let mut book = OrderBook::new();
+    book.add_order(Order::new(1, Side::Sell, 15, 5000, 1000, 1)).unwrap();
+
+    let report = book.submit(Order::new(2, Side::Buy, 50, 5000, 1001, 1).with_peak(10)).unwrap();
+    assert_eq!(report.filled_quantity, 15);
+    assert_eq!(report.remaining_quantity, 35);
+    assert!(report.rested);
+    assert_eq!(book.best_bid(), Some((5000, 10)));
+    assert_eq!(book.get_order(2).unwrap().hidden_quantity, 25);
+}
+
+#[test]
+fn test_iceberg_updates_change_the_reserve() {
+    let mut book = iceberg_book();
+    book.update_order(1, 50).unwrap();
+    assert_eq!(book.get_order(1).unwrap().hidden_quantity, 30);
+    assert_eq!(bid_queue(&book), vec![(1, 20), (2, 10)]);
+
+    // A total above the current one loses priority on amend
+    let result = book.amend_order(1, 5000, 60).unwrap();
+    assert!(result.priority_lost);
+    assert_eq!((result.old_quantity, result.new_quantity), (50, 60));
+    assert_eq!(bid_queue(&book), vec![(2, 10), (1, 20)]);
+    assert_eq!(book.bids().next().unwrap().hidden_size, 40);
+
+    book.remove_order(2).unwrap();
+    book.remove_order(1).unwrap();
+    assert_eq!(book.total_levels(), 0);
+
+    // The peak itself must be a valid quantity
+    let mut book = OrderBook::with_config(InstrumentConfig::new(1, 10));
+    let odd_peak = Order::new(3, Side::Buy, 100, 5000, 1000, 1).with_peak(15);
+    assert_eq!(book.add_order(odd_peak), Err(OrderBookError::InvalidLot(15)));
+}
+
+#[test]
+fn test_iceberg_randomized_peaks() {
+    let slices = |seed: u64| -> Vec<u64> {
+        let mut book = OrderBook::with_config(InstrumentConfig::new(1, 5));
+        book.set_peak_seed(seed);
+        let iceberg = Order::new(1, Side::Buy, 1000, 5000, 1000, 1).with_peak(50).with_peak_variance(20);
+        book.add_order(iceberg).unwrap();
+        let mut slices = Vec::new();
+        for id in 2..10 {
+            book.submit(Order::new(id, Side::Sell, book.get_order(1).unwrap().quantity, 5000, 1000, 1)).unwrap();
+            slices.push(book.get_order(1).unwrap().quantity);
+        }
+        slices
+    };
+
+    let first = slices(7);
+    assert!(first.iter().all(|&peak| (30..=70).contains(&peak) && peak % 5 == 0));
+    assert!(first.iter().any(|&peak| peak != first[0]));
+    assert_eq!(first, slices(7));
+}
+
+// ============================================================================
+// Hidden order tests
+// ============================================================================
+
+/// Hidden bid 1 of 50 ahead in time of a displayed bid 2 of 10, both at 5000,
+/// plus a hidden-only bid level at 5010
+fn hidden_book() -> OrderBook {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+    book.add_order(Order::new(1, Side::Buy, 50, 5000, 1000, 1).with_hidden(true)).unwrap();
+    book.add_order(Order::new(2, Side::Buy, 10, 5000, 1001, 1)).unwrap();
+    book.add_order(Order::new(3, Side::Buy, 30, 5010, 1002, 1).with_hidden(true)).unwrap();
+    book
+}
+
+#[test]
+fn test_hidden_orders_are_not_displayed() {
+    let book = hidden_book();
+    assert_eq!(book.best_bid(), Some((5000, 10)));
+    assert_eq!(book.volume_at_price(5000), Some(10));
+    assert_eq!(book.orders_at_price(5000), Some(1));
+    assert_eq!(book.volume_at_price(5010), None);
+    assert_eq!(book.orders_at_price(5010), None);
+    assert_eq!(book.get_levels(None).0, vec![(5000, 10)]);
+
+    assert_eq!(book.volume_at_price_with_hidden(5000), Some(60));
+    assert_eq!(book.volume_at_price_with_hidden(5010), Some(30));
+    assert_eq!(book.get_levels_with_hidden(None).0, vec![(5010, 30), (5000, 60)]);
+    assert_eq!(book.total_orders(), 3);
+    assert_eq!(book.total_levels(), 2);
+}
+
+#[test]
+fn test_hidden_orders_queue_behind_displayed() {
+    let mut book = hidden_book();
+    book.add_order(Order::new(4, Side::Buy, 5, 5000, 1003, 1)).unwrap();
+    assert_eq!(bid_queue(&book), vec![(2, 10), (4, 5), (1, 50)]);
+
+    // The hidden level still trades first by price, then displayed before hidden
+    let report = book.submit(Order::new(5, Side::Sell, 60, 5000, 1004, 1)).unwrap();
+    let fills: Vec<_> = report.trades.iter().map(|t| (t.passive_order_id, t.price, t.quantity)).collect();
+    assert_eq!(fills, vec![(3, 5010, 30), (2, 5000, 10), (4, 5000, 5), (1, 5000, 15)]);
+    assert_eq!(book.best_bid(), None);
+    assert_eq!(book.get_levels_with_hidden(None).0, vec![(5000, 35)]);
+
+    // New displayed orders still queue ahead of the hidden remainder
+    book.add_order(Order::new(6, Side::Buy, 5, 5000, 1005, 1)).unwrap();
+    assert_eq!(bid_queue(&book), vec![(6, 5), (1, 35)]);
+}
+
+#[test]
+fn test_hidden_orders_record_no_order_events() {
+    let mut book = OrderBook::new();
+    book.set_events_enabled(true);
+    book.set_time(1000);
+    book.add_order(Order::new(1, Side::Sell, 20, 5000, 1000, 1).with_hidden(true)).unwrap();
+    book.update_order(1, 15).unwrap();
+    assert!(book.drain_events().is_empty());
+
+    // The level is only reported while it shows a displayed order
+    book.add_order(Order::new(2, Side::Sell, 5, 5000, 1000, 1)).unwrap();
+    book.remove_order(2).unwrap();
+    let levels: Vec<_> = book.drain_events().into_iter()
+        .filter(|e| matches!(e, BookEvent::LevelCreated { .. } | BookEvent::LevelRemoved { .. }))
+        .collect();
+    assert_eq!(levels, vec![
+        BookEvent::LevelCreated { side: Side::Sell, price: 5000, timestamp: 1000 },
+        BookEvent::LevelRemoved { side: Side::Sell, price: 5000, timestamp: 1000 },
+    ]);
+
+    book.submit(Order::new(3, Side::Buy, 15, 5000, 1001, 1)).unwrap();
+    assert!(book.drain_events().is_empty());
+    assert_eq!(book.total_levels(), 0);
+}
+
+#[test]
+fn test_hidden_iceberg_is_rejected() {
+    let mut book = OrderBook::new();
+    let order = Order::new(1, Side::Buy, 100, 5000, 1000, 1).with_peak(10).with_hidden(true);
+    assert_eq!(book.add_order(order.clone()), Err(OrderBookError::InvalidOrderType(OrderType::Limit)));
+    assert_eq!(book.submit(order), Err(OrderBookError::InvalidOrderType(OrderType::Limit)));
+}
+
+// ============================================================================
+// Stop order tests
+// ============================================================================
+
+/// One ask of 10 at each price from 101 to 105, with a last trade at 100
+fn stop_book() -> OrderBook {
+    let mut book = OrderBook::new();
+    book.set_time(1000);
+    book.add_order(Order::new(1, Side::Sell, 5, 100, 1000, 1)).unwrap();
+    book.submit(Order::new(2, Side::Buy, 5, 100, 1000, 1)).unwrap();
+    for price in 101..=105 {
+        book.add_order(Order::new(price as u64, Side::Sell, 10, price, 1000, 1)).unwrap();
+    }
+    book
+}
+
+#[test]
+fn test_stop_parks_until_triggered() {
+    let mut book = stop_book();
+    book.set_events_enabled(true);
+    assert_eq!(book.last_trade_price(), Some(100));
+
+    let report = book.submit(Order::market(10, Side::Buy, 15, 1001, 1).with_stop(101)).unwrap();
+    assert!(report.parked && !report.rested);
+    assert_eq!((report.remaining_quantity, report.cancelled_quantity()), (15, 0));
+    assert_eq!(book.total_stop_orders(), 1);
+    assert!(!book.contains_order(10));
+    assert_eq!(book.get_stop_order(10).unwrap().stop_price, Some(101));
+    assert!(book.drain_events().is_empty());
+
+    // A trade at the stop price fires it as a market order
+    book.set_time(2000);
+    let report = book.submit(Order::new(11, Side::Buy, 4, 101, 2000, 1)).unwrap();
+    assert_eq!(report.triggered.len(), 1);
+    let fired = &report.triggered[0];
+    assert_eq!(fired.order_id, 10);
+    assert_eq!(fired.trades.iter().map(|t| (t.price, t.quantity)).collect::<Vec<_>>(), vec![(101, 6), (102, 9)]);
+    assert_eq!(book.last_trade_price(), Some(102));
+    assert_eq!(book.total_stop_orders(), 0);
+
+    let events = book.drain_events();
+    let trigger = events.iter().position(|e| matches!(e, BookEvent::StopTriggered { .. })).unwrap();
+    assert_eq!(
+        events[trigger],
+        BookEvent::StopTriggered { order_id: 10, side: Side::Buy, stop_price: 101, timestamp: 2000 }
+    );
+    assert!(events[..trigger].iter().any(|e| matches!(e, BookEvent::OrderFilled { aggressor_order_id: 11, .. })));
+}
+
+#[test]
+fn test_stop_cascade_order() {
+    let mut book = stop_book();
+    book.submit(Order::market(20, Side::Buy, 10, 1001, 1).with_stop(103)).unwrap();
+    book.submit(Order::market(21, Side::Buy, 5, 1002, 1).with_stop(102)).unwrap();
+    book.submit(Order::market(22, Side::Buy, 5, 1003, 1).with_stop(102)).unwrap();
+    book.submit(Order::market(23, Side::Buy, 5, 1004, 1).with_stop(105)).unwrap();
+    book.submit(Order::market(24, Side::Sell, 5, 1005, 1).with_stop(90)).unwrap();
+
+    // 21 and 22 fire at 102 in arrival order; 22 trades at 103 and fires 20,
+    // which reaches 104 but not the stop at 105
+    let report = book.submit(Order::new(25, Side::Buy, 15, 102, 1006, 1)).unwrap();
+    let fired: Vec<_> = report.triggered.iter()
+        .map(|r| (r.order_id, r.trades.last().unwrap().price))
+        .collect();
+    assert_eq!(fired, vec![(21, 102), (22, 103), (20, 104)]);
+    assert!(report.triggered.iter().all(|r| r.triggered.is_empty()));
+    assert_eq!(book.stop_orders().map(|o| o.id).collect::<Vec<_>>(), vec![23, 24]);
+}
+
+#[test]
+fn test_stop_limit_and_immediate_trigger() {
+    let mut book = stop_book();
+
+    // A sell stop-limit below the market that is already triggered is
+    // submitted straight away
+    let stop_limit = Order::new(30, Side::Sell, 10, 102, 1001, 1).with_stop(100);
+    let report = book.submit(stop_limit).unwrap();
+    assert!(!report.parked && report.rested);
+    assert_eq!(book.best_ask(), Some((101, 10)));
+    assert_eq!(book.get_order(30).unwrap().stop_price, None);
+
+    // A buy stop-limit rests at its limit price once fired
+    book.submit(Order::new(31, Side::Buy, 20, 101, 1002, 1).with_stop(101)).unwrap();
+    let report = book.submit(Order::new(32, Side::Buy, 5, 101, 1003, 1)).unwrap();
+    assert_eq!(report.triggered[0].filled_quantity, 5);
+    assert!(report.triggered[0].rested);
+    assert_eq!(book.best_bid(), Some((101, 15)));
+}
+
+#[test]
+fn test_parked_stop_cancel_and_validation() {
+    let mut book = stop_book();
+    book.submit(Order::market(40, Side::Sell, 10, 1001, 1).with_stop(95)).unwrap();
+
+    let stop = Order::market(40, Side::Sell, 10, 1002, 1).with_stop(94);
+    assert_eq!(book.submit(stop), Err(OrderBookError::OrderAlreadyExists(40)));
+    let resting = Order::new(40, Side::Buy, 10, 90, 1002, 1);
+    assert_eq!(book.add_order(resting), Err(OrderBookError::OrderAlreadyExists(40)));
+    let stop = Order::new(41, Side::Buy, 10, 90, 1002, 1).with_stop(110);
+    assert_eq!(book.add_order(stop), Err(OrderBookError::InvalidOrderType(OrderType::Limit)));
+
+    let cancelled = book.remove_order(40).unwrap();
+    assert_eq!((cancelled.id, cancelled.stop_price), (40, Some(95)));
+    assert_eq!(book.total_stop_orders(), 0);
+    assert_eq!(book.remove_order(40), Err(OrderBookError::OrderNotFound(40)));
+
+    let mut book = OrderBook::with_config(InstrumentConfig::new(5, 1));
+    let off_tick = Order::market(42, Side::Buy, 10, 1000, 1).with_stop(101);
+    assert_eq!(book.submit(off_tick), Err(OrderBookError::InvalidTick(101)));
+}
+
+#[test]
+fn test_triggered_stop_rejected_under_new_config() {
+    let mut book = stop_book();
+    book.set_events_enabled(true);
+    book.submit(Order::new(60, Side::Buy, 10, 101, 1001, 1).with_stop(101)).unwrap();
+    book.set_config(InstrumentConfig::new(5, 1)).unwrap();
+
+    // The stop-limit's price is off the new tick once it fires, so it is
+    // dropped rather than submitted
+    let report = book.submit(Order::new(61, Side::Buy, 5, 105, 1002, 1)).unwrap();
+    assert_eq!(report.rejection, None);
+    let fired = &report.triggered[0];
+    assert_eq!((fired.order_id, fired.rejection.clone()), (60, Some(OrderBookError::InvalidTick(101))));
+    assert!(fired.trades.is_empty() && !fired.rested);
+    assert_eq!(fired.remaining_quantity, 10);
+    assert_eq!(book.total_stop_orders(), 0);
+    assert!(!book.contains_order(60));
+
+    let events = book.drain_events();
+    let trigger = events.iter().position(|e| matches!(e, BookEvent::StopTriggered { order_id: 60, .. })).unwrap();
+    assert_eq!(events[trigger + 1], BookEvent::StopRejected { order_id: 60, timestamp: 1000 });
+}
+
+#[test]
+fn test_stop_limit_price_checked_when_parked() {
+    let mut book = OrderBook::<LadderIndex>::with_backend(InstrumentConfig::default());
+    book.add_order(Order::new(1, Side::Sell, 10, 100, 0, 1)).unwrap();
+    let price = 100 + LadderIndex::MAX_SLOTS as i64;
+    let stop_limit = Order::new(2, Side::Sell, 10, price, 0, 1).with_stop(90);
+    assert_eq!(book.submit(stop_limit), Err(OrderBookError::PriceOutOfBand(price)));
+    assert_eq!(book.total_stop_orders(), 0);
+}
+
+#[test]
+fn test_stops_wait_for_continuous_trading() {
+    let mut book = stop_book();
+    book.start_call_auction(None).unwrap();
+    let report = book.submit(Order::market(50, Side::Buy, 10, 1001, 1).with_stop(100)).unwrap();
+    assert!(report.parked);
+
+    book.submit(Order::new(51, Side::Buy, 10, 101, 1002, 1)).unwrap();
+    let result = book.uncross().unwrap();
+    assert_eq!(result.price, Some(101));
+    assert_eq!(result.triggered.len(), 1);
+    assert_eq!(result.triggered[0].trades[0].price, 102);
+}
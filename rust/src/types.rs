@@ -8,9 +8,11 @@ use serde::{Deserialize, Serialize};
 /// Unique identifier for orders
 pub type OrderId = u64;
 
-/// Price type - using u64 to represent price in smallest units (e.g., cents)
-/// This avoids floating point precision issues in financial calculations
-pub type Price = u64;
+/// Price type - using i64 to represent price in smallest units (e.g., cents)
+/// This avoids floating point precision issues in financial calculations.
+/// Prices are signed so that calendar spreads and negative futures prices can
+/// be booked; instruments reject non-positive prices unless configured otherwise.
+pub type Price = i64;
 
 /// Quantity type - using u64 for order sizes
 pub type Quantity = u64;
@@ -21,6 +23,12 @@ pub type Timestamp = u64;
 /// Exchange identifier
 pub type ExchangeId = u32;
 
+/// Instrument (symbol) identifier used to route orders to their book
+pub type InstrumentId = u32;
+
+/// Participant (owner or account) identifier used for self-trade prevention
+pub type OwnerId = u64;
+
 /// Order side (Buy or Sell)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
@@ -60,6 +68,138 @@ impl Side {
     }
 }
 
+/// Order type, describing how an order interacts with the book on submission
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum OrderType {
+    /// Match what crosses, rest the remainder at the limit price
+    #[default]
+    Limit,
+    /// Match against any price, cancel the remainder
+    Market,
+    /// Match what crosses at the limit price, cancel the remainder
+    ImmediateOrCancel,
+    /// Match the full quantity at the limit price or nothing at all
+    FillOrKill,
+}
+
+impl fmt::Display for OrderType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderType::Limit => write!(f, "Limit"),
+            OrderType::Market => write!(f, "Market"),
+            OrderType::ImmediateOrCancel => write!(f, "IOC"),
+            OrderType::FillOrKill => write!(f, "FOK"),
+        }
+    }
+}
+
+impl OrderType {
+    /// Returns true if an unfilled remainder may rest in the book
+    pub fn can_rest(&self) -> bool {
+        matches!(self, OrderType::Limit)
+    }
+
+    /// Returns true if the order carries a meaningful limit price
+    pub fn has_limit_price(&self) -> bool {
+        !matches!(self, OrderType::Market)
+    }
+}
+
+/// What to do when an incoming order would trade against a resting order of the same owner
+///
+/// The incoming order's mode decides. Orders without an owner never trigger it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum SelfTradePrevention {
+    /// Cancel the rest of the incoming order, keep the resting order
+    #[default]
+    CancelNewest,
+    /// Cancel the resting order and continue matching
+    CancelOldest,
+    /// Cancel both the rest of the incoming order and the resting order
+    CancelBoth,
+    /// Reduce both by the smaller quantity, cancelling whichever reaches zero
+    DecrementAndCancel,
+}
+
+impl fmt::Display for SelfTradePrevention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelfTradePrevention::CancelNewest => write!(f, "CancelNewest"),
+            SelfTradePrevention::CancelOldest => write!(f, "CancelOldest"),
+            SelfTradePrevention::CancelBoth => write!(f, "CancelBoth"),
+            SelfTradePrevention::DecrementAndCancel => write!(f, "DecrementAndCancel"),
+        }
+    }
+}
+
+/// Trading session state of a book
+///
+/// The phase decides which operations the book accepts and whether submitted
+/// orders are matched. Books start in `Continuous`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub enum TradingPhase {
+    /// Before the session: orders are collected but never matched
+    PreOpen,
+    /// Call auction: orders are collected and uncrossed at a single price
+    AuctionCall,
+    /// Continuous matching
+    #[default]
+    Continuous,
+    /// Trading suspended: orders can only be cancelled
+    Halted,
+    /// Session over: the book accepts nothing until the next pre-open
+    Closed,
+}
+
+impl fmt::Display for TradingPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TradingPhase::PreOpen => write!(f, "PreOpen"),
+            TradingPhase::AuctionCall => write!(f, "AuctionCall"),
+            TradingPhase::Continuous => write!(f, "Continuous"),
+            TradingPhase::Halted => write!(f, "Halted"),
+            TradingPhase::Closed => write!(f, "Closed"),
+        }
+    }
+}
+
+impl TradingPhase {
+    /// Whether orders can be added or submitted
+    pub fn accepts_orders(&self) -> bool {
+        matches!(self, TradingPhase::PreOpen | TradingPhase::AuctionCall | TradingPhase::Continuous)
+    }
+
+    /// Whether resting orders can be modified
+    pub fn accepts_modifications(&self) -> bool {
+        self.accepts_orders()
+    }
+
+    /// Whether resting orders can be cancelled
+    pub fn accepts_cancels(&self) -> bool {
+        !matches!(self, TradingPhase::Closed)
+    }
+
+    /// Whether submitted orders are matched on arrival
+    pub fn matches_orders(&self) -> bool {
+        matches!(self, TradingPhase::Continuous)
+    }
+
+    /// Whether the book may move from this phase to a different phase `next`
+    ///
+    /// A closed book only reopens through pre-open; every other change is
+    /// allowed. Entering continuous trading additionally needs an uncrossed
+    /// book, which the book checks itself.
+    pub fn can_transition_to(&self, next: TradingPhase) -> bool {
+        match self {
+            TradingPhase::Closed => next == TradingPhase::PreOpen,
+            _ => true,
+        }
+    }
+}
+
 /// Trade information when orders are matched
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
@@ -99,8 +239,8 @@ impl Trade {
     }
 
     /// Calculate the trade value (price * quantity)
-    pub fn value(&self) -> u128 {
-        self.price as u128 * self.quantity as u128
+    pub fn value(&self) -> i128 {
+        self.price as i128 * self.quantity as i128
     }
 }
 
@@ -114,3 +254,21 @@ impl fmt::Display for Trade {
         )
     }
 }
+
+/// Result of amending a resting order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+pub struct AmendResult {
+    /// ID of the amended order
+    pub order_id: OrderId,
+    /// Price before the amend
+    pub old_price: Price,
+    /// Price after the amend
+    pub new_price: Price,
+    /// Quantity before the amend, including any iceberg reserve
+    pub old_quantity: Quantity,
+    /// Quantity after the amend, including any iceberg reserve
+    pub new_quantity: Quantity,
+    /// Whether the order lost its time priority and moved to the back of the queue
+    pub priority_lost: bool,
+}